@@ -0,0 +1,46 @@
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::{Config, Module};
+use super::{Bar, Registry};
+
+/// Builds a [`Bar`] with modules of your own next to the built-in ones
+///
+/// ```ignore
+/// let builder = Bar::builder()
+///     .module("weather", |_name, config: &WeatherConfig, tx| WeatherModule::new(config, tx));
+/// let bar = builder.build(&config, tx);
+/// ```
+///
+/// The builder can be kept around to rebuild the bar, e.g. after the
+/// config was reloaded.
+#[derive(Default, Clone)]
+pub struct BarBuilder {
+    registry: Registry,
+}
+
+impl BarBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a module kind, used for every entry named `kind` or
+    /// `kind#instance` in a section
+    ///
+    /// The constructor gets the name of the entry, its config table
+    /// deserialized into `C` and the sender used to ask for a redraw.
+    /// A registered kind takes precedence over a built-in module with
+    /// the same name.
+    pub fn module<C, M, F>(mut self, kind: &str, constructor: F) -> Self
+    where
+        C: DeserializeOwned,
+        M: Module + 'static,
+        F: Fn(&str, &C, UnboundedSender<()>) -> M + Send + Sync + 'static,
+    {
+        self.registry.register(kind, constructor);
+        self
+    }
+
+    pub fn build(&self, config: &Config, tx: UnboundedSender<()>) -> Bar {
+        Bar::with_registry(config, &self.registry, tx)
+    }
+}
@@ -1,107 +1,697 @@
-use std::sync::Arc;
-use std::sync::mpsc::Sender;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::UnixListener;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use tokio::time::{Duration, Instant, sleep_until};
+use crate::config::{OutputConfig, Overflow, module_kind};
 use crate::Config;
 use crate::{Module, ModuleOutput};
-use super::build_modules;
+use super::{placeholders, width};
+use super::{
+    BarBuilder, Built, Frame, Layout, ModuleEntry, Monitor, Monitors, Palette, Piece, Reduction, Registry,
+    SectionStyle, build_modules,
+};
+
+/// Outputs of the visible modules of a section, with their entry
+type Section<'a> = Vec<(&'a ModuleEntry, ModuleOutput)>;
 
 pub struct Bar {
-    left: Vec<Arc<dyn Module + Sync + Send>>,
-    center: Vec<Arc<dyn Module + Sync + Send>>,
-    right: Vec<Arc<dyn Module + Sync + Send>>,
+    /// Every bar drawn by this instance, sharing their modules
+    layouts: Vec<Layout>,
+
+    instance: String,
+    error_format: String,
+    error_color: Option<String>,
+    palette: Palette,
+    monitors: Monitors,
+
+    tx: UnboundedSender<()>,
+    hidden: HashSet<String>,
+    started: Instant,
+    /// Time between marquee steps of the fastest scrolling module
+    marquee_step: Option<Duration>,
+    tasks: Mutex<Vec<JoinHandle<()>>>,
+}
+
+/// Grapheme clusters scrolled per second when `marquee_speed` is not set
+const DEFAULT_MARQUEE_SPEED: u32 = 4;
 
-    separator: String,
-    frontend: String,
+/// Summary of a loaded module, as reported by the `list` command
+#[derive(Serialize, Deserialize)]
+pub struct ModuleInfo {
+    /// Name used in the section, e.g. `filesystem#home`
+    pub name: String,
+    /// Kind of module, e.g. `filesystem`
+    pub module: String,
+    /// Instance name, e.g. `home`
+    pub instance: Option<String>,
+    /// Section of the module, prefixed with the name of its bar
+    /// when it is not the top-level one, e.g. `bottom:left`
+    pub section: String,
+    pub interval: Option<u64>,
+    pub signal_id: Option<u8>,
+    pub signal: Option<u8>,
+    pub groups: Vec<String>,
+    pub hidden: bool,
 }
 
 impl Bar {
-    pub fn new(config: &Config, tx: Sender<()>) -> Self {
+    /// Build a bar with the built-in modules only
+    pub fn new(config: &Config, tx: UnboundedSender<()>) -> Self {
+        BarBuilder::new().build(config, tx)
+    }
+
+    pub fn builder() -> BarBuilder {
+        BarBuilder::new()
+    }
+
+    pub(super) fn with_registry(config: &Config, registry: &Registry, tx: UnboundedSender<()>) -> Self {
+        let mut built = Built::new();
+        let layouts: Vec<Layout> = config
+            .layouts()
+            .into_iter()
+            .map(|(name, bar)| {
+                let sections = [&bar.left, &bar.center, &bar.right]
+                    .map(|items| build_modules(items, &config.modules, registry, &tx, &mut built));
+                Layout::new(name, &bar, config, sections)
+            })
+            .collect();
+        let marquee_step = marquee_step(
+            layouts
+                .iter()
+                .flat_map(|layout| layout.sections.iter().flatten())
+                .flat_map(|entry| std::iter::once(entry).chain(&entry.members)),
+        );
+        let monitors = Monitors::new(layouts.iter().any(|layout| layout.per_monitor));
         Self {
-            left: build_modules(&config.left, &config.modules, &tx),
-            center: build_modules(&config.center, &config.modules, &tx),
-            right: build_modules(&config.right, &config.modules, &tx),
-            separator: config.separator.clone(),
-            frontend: config.frontend.clone(),
+            layouts,
+            instance: config.instance.clone(),
+            error_format: config.error_format.clone(),
+            error_color: config.error_color.clone(),
+            palette: Palette::new(config.colors.clone()),
+            monitors,
+            tx,
+            hidden: HashSet::new(),
+            started: Instant::now(),
+            marquee_step,
+            tasks: Mutex::new(Vec::new()),
         }
     }
 
-    pub async fn construct(&self) -> String {
-        match self.frontend.as_str() {
-            "lemonbar" => self.construct_lemonbar().await,
-            _ => {
-                eprintln!("warning: frontend {} not implemented", &self.frontend);
-                self.construct_generic().await
+    /// Render every bar
+    pub async fn construct(&self) -> Vec<Frame> {
+        let mut frames = Vec::with_capacity(self.layouts.len());
+        for layout in &self.layouts {
+            if layout.frontend != "lemonbar" {
+                eprintln!("warning: frontend {} not implemented", &layout.frontend);
             }
+            frames.push(Frame {
+                output: layout.output.clone(),
+                text: self.render(layout, false).await,
+            });
+        }
+        frames
+    }
+
+    /// Render the bars where something scrolls after a marquee step,
+    /// only querying the modules of sections that scroll
+    pub async fn construct_scrolled(&self) -> Vec<Frame> {
+        let mut frames = Vec::new();
+        for layout in self.layouts.iter().filter(|layout| layout.scrolling()) {
+            frames.push(Frame {
+                output: layout.output.clone(),
+                text: self.render(layout, true).await,
+            });
         }
+        frames
+    }
+
+    /// Where the bars are written
+    pub fn outputs(&self) -> Vec<&OutputConfig> {
+        self.layouts.iter().map(|layout| &layout.output).collect()
+    }
+
+    /// Wait for the next marquee step, or forever when nothing scrolls
+    pub async fn scroll_tick(&self) {
+        let scrolling = self.layouts.iter().any(Layout::scrolling);
+        let Some(step) = self.marquee_step.filter(|_| scrolling) else {
+            return std::future::pending().await;
+        };
+        // steps are counted from the start, so frequent redraws in
+        // between do not hold scrolling back
+        let steps = self.started.elapsed().as_millis() / step.as_millis() + 1;
+        sleep_until(self.started + step * steps as u32).await;
     }
 
     pub fn start_modules(&self) {
-        for module in self.left.iter().chain(self.center.iter()).chain(self.right.iter()) {
+        let mut tasks = self.tasks.lock().unwrap();
+        for module in self.entries().flat_map(ModuleEntry::modules) {
             let module_clone = Arc::clone(module);
-            tokio::spawn(async move {
+            tasks.push(tokio::spawn(async move {
                 module_clone.run().await;
-            });
+            }));
         }
+
+        tasks.extend(self.start_signal_listeners());
+        tasks.extend(self.palette.watch(self.tx.clone()));
+        tasks.extend(self.monitors.watch(self.tx.clone()));
     }
 
-    /// Listens for commands sent using the --update flag
-    pub async fn start_command_listener(&self, socket_path: &str) {
-        let _ = std::fs::remove_file(socket_path);
-        let listener = UnixListener::bind(socket_path).unwrap();
+    /// Refresh modules that set `signal = N` whenever `SIGRTMIN+N` is
+    /// received, like `pkill -RTMIN+N barrs`
+    fn start_signal_listeners(&self) -> Vec<JoinHandle<()>> {
+        let mut by_signal: BTreeMap<u8, Vec<Arc<dyn Module + Send + Sync>>> = BTreeMap::new();
+        for entry in self.entries() {
+            if let Some(n) = entry.common.signal {
+                by_signal.entry(n).or_default().extend(entry.modules().cloned());
+            }
+        }
 
-        let modules: Vec<_> = self.left
-            .iter()
-            .chain(self.center.iter())
-            .chain(self.right.iter())
-            .cloned()
-            .collect();
+        let mut tasks = Vec::new();
+        for (n, modules) in by_signal {
+            let signum = libc::SIGRTMIN() + n as i32;
+            if signum > libc::SIGRTMAX() {
+                eprintln!("warning: signal {n} is out of range, SIGRTMIN+{n} does not exist");
+                continue;
+            }
 
-        tokio::spawn(async move {
-            loop {
-                let (mut stream, _) = listener.accept().await.unwrap();
-                let mut buf = [0u8; 64];
-                let n = stream.read(&mut buf).await.unwrap();
-
-                let msg = std::str::from_utf8(&buf[..n]).unwrap().trim();
-
-                if let Some(id) = msg.strip_prefix("update ")
-                && let Ok(id) = id.parse::<u8>() {
-                    for m in &modules {
-                        if m.signal_id() == Some(id) {
-                            let m = m.clone();
-                            tokio::spawn(async move {
-                                m.run().await;
-                            });
-                        }
+            let mut stream = match signal(SignalKind::from_raw(signum)) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("warning: could not listen for SIGRTMIN+{n}: {e}");
+                    continue;
+                }
+            };
+
+            tasks.push(tokio::spawn(async move {
+                while stream.recv().await.is_some() {
+                    for module in &modules {
+                        module.update().await;
                     }
                 }
+            }));
+        }
+        tasks
+    }
+
+    /// Stop all module tasks
+    pub fn shutdown(&self) {
+        for task in self.tasks.lock().unwrap().drain(..) {
+            task.abort();
+        }
+    }
+
+    /// Every module of the bars, including the modules of groups
+    fn entries(&self) -> impl Iterator<Item = &ModuleEntry> {
+        let mut seen = HashSet::new();
+        self.layouts
+            .iter()
+            .flat_map(|layout| layout.sections.iter().flatten())
+            .flat_map(|entry| std::iter::once(entry).chain(&entry.members))
+            // a module shared by several sections or bars is only
+            // updated, toggled or started once
+            .filter(move |entry| seen.insert(Arc::as_ptr(&entry.module) as *const ()))
+    }
+
+    fn find(&self, target: &str) -> Result<&ModuleEntry> {
+        self.entries()
+            .find(|entry| entry.name == target)
+            .ok_or_else(|| anyhow::anyhow!("no module named {target}"))
+    }
+
+    /// Modules referred to by `target`, which is either a numeric
+    /// `signal_id` or anything accepted by [`ModuleEntry::matches`]
+    fn resolve(&self, target: &str) -> Result<Vec<&ModuleEntry>> {
+        let matched: Vec<&ModuleEntry> = match target.parse::<u8>() {
+            Ok(id) => self.entries().filter(|entry| entry.module.signal_id() == Some(id)).collect(),
+            Err(_) => self.entries().filter(|entry| entry.matches(target)).collect(),
+        };
+
+        if matched.is_empty() {
+            anyhow::bail!("no module matches {target}");
+        }
+        Ok(matched)
+    }
 
-                let _ = stream.write_all(b"ok\n").await;
+    /// Like [`Self::resolve`], but a group also brings in its modules.
+    /// Toggling or clicking a group is left to its label, and hiding the
+    /// label already hides them
+    fn resolve_with_members(&self, target: &str) -> Result<Vec<&ModuleEntry>> {
+        let mut matched = self.resolve(target)?;
+        let members: Vec<&ModuleEntry> = matched.iter().flat_map(|entry| &entry.members).collect();
+        for member in members {
+            if !matched.iter().any(|entry| Arc::ptr_eq(&entry.module, &member.module)) {
+                matched.push(member);
             }
+        }
+        Ok(matched)
+    }
+
+    /// Refresh every module matching `target` right away
+    pub fn update(&self, target: &str) -> Result<()> {
+        for module in self.resolve_with_members(target)?.into_iter().flat_map(ModuleEntry::modules) {
+            let module = Arc::clone(module);
+            tokio::spawn(async move {
+                module.update().await;
+            });
+        }
+        Ok(())
+    }
+
+    /// Push a value to every ipc module matching `target`
+    pub fn set(&self, target: &str, text: &str, color: Option<&str>, expire: Option<u64>) -> Result<()> {
+        // ipc modules can also be addressed by their instance name alone
+        let entries = self.entries().filter(|entry| {
+            entry.matches(target)
+                || entry.name.split_once('#').is_some_and(|(_, instance)| instance == target)
         });
+
+        let mut matched = false;
+        for entry in entries {
+            if entry.module.set_value(text.to_string(), color.map(str::to_string), expire) {
+                let module = Arc::clone(&entry.module);
+                tokio::spawn(async move {
+                    module.update().await;
+                });
+                matched = true;
+            }
+        }
+
+        if !matched {
+            anyhow::bail!("no ipc module matches {target}");
+        }
+        Ok(())
+    }
+
+    /// Switch every module matching `target` between its formats and
+    /// its `format_alt*` formats
+    pub fn toggle(&self, target: &str) -> Result<()> {
+        let mut matched = false;
+        for entry in self.resolve(target)? {
+            if entry.module.toggle() {
+                let module = Arc::clone(&entry.module);
+                tokio::spawn(async move {
+                    module.update().await;
+                });
+                matched = true;
+            }
+        }
+
+        if !matched {
+            anyhow::bail!("no module with format_alt matches {target}");
+        }
+        Ok(())
+    }
+
+    /// Expand or collapse the group named `target`
+    pub fn set_expanded(&self, target: &str, expanded: bool) -> Result<()> {
+        let drawer = self
+            .entries()
+            .find(|entry| entry.name == target && !entry.members.is_empty())
+            .and_then(|entry| entry.drawer.as_ref())
+            .ok_or_else(|| anyhow::anyhow!("no group named {target}"))?;
+        drawer.set_expanded(expanded);
+        let _ = self.tx.send(());
+        Ok(())
+    }
+
+    /// Send a mouse button press to every module matching `target` that
+    /// reacts to the button
+    pub fn click(&self, target: &str, button: u8) -> Result<()> {
+        let mut matched = false;
+        for entry in self.resolve(target)? {
+            if entry.module.buttons().contains(&button) {
+                let module = Arc::clone(&entry.module);
+                tokio::spawn(async move {
+                    module.click(button).await;
+                });
+                matched = true;
+            }
+        }
+
+        if !matched {
+            anyhow::bail!("no module matching {target} reacts to button {button}");
+        }
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<ModuleInfo> {
+        self.layouts
+            .iter()
+            .flat_map(|layout| {
+                ["left", "center", "right"]
+                    .into_iter()
+                    .zip(&layout.sections)
+                    .map(|(section, entries)| (layout.section_name(section), entries))
+            })
+            .flat_map(|(section, entries)| entries.iter().map(move |entry| (section.clone(), entry)))
+            .flat_map(|(section, entry)| {
+                std::iter::once((section.clone(), entry)).chain(entry.members.iter().map(move |member| (section.clone(), member)))
+            })
+            .map(|(section, entry)| ModuleInfo {
+                name: entry.name.clone(),
+                module: if entry.members.is_empty() { module_kind(&entry.name) } else { "group" }.to_string(),
+                instance: entry.name.split_once('#').map(|(_, instance)| instance.to_string()),
+                section,
+                interval: entry.module.interval(),
+                signal_id: entry.module.signal_id(),
+                signal: entry.common.signal,
+                groups: entry.common.groups.clone(),
+                hidden: self.hidden.contains(&entry.name),
+            })
+            .collect()
+    }
+
+    pub async fn get(&self, target: &str) -> Result<ModuleOutput> {
+        let entry = self.find(target)?;
+        let mut output = self.style(entry, entry.module.get_value().await);
+        output.value = self.fit(entry, output.value);
+        Ok(output)
+    }
+
+    fn resolve_names(&self, target: &str) -> Result<Vec<String>> {
+        Ok(self.resolve(target)?.iter().map(|entry| entry.name.clone()).collect())
+    }
+
+    pub fn hide(&mut self, target: &str) -> Result<()> {
+        let names = self.resolve_names(target)?;
+        self.hidden.extend(names);
+        Ok(())
+    }
+
+    pub fn show(&mut self, target: &str) -> Result<()> {
+        for name in self.resolve_names(target)? {
+            self.hidden.remove(&name);
+        }
+        Ok(())
     }
 
-    async fn collect_sections(
+    /// Render every section, or only those that scrolled last time,
+    /// reusing the previous rendering of the others
+    async fn render(&self, layout: &Layout, scrolled: bool) -> String {
+        if layout.per_monitor && layout.frontend == "lemonbar" {
+            let monitors = self.monitors.list();
+            // without RandR, the bar is drawn once as usual
+            if !monitors.is_empty() {
+                return self.render_monitors(layout, &monitors).await;
+            }
+        }
+
+        let mut rendered = layout.rendered.lock().unwrap().clone();
+        let mut sections = [None, None, None];
+        for ((section, previous), entries) in sections.iter_mut().zip(&mut rendered).zip(&layout.sections) {
+            if scrolled && !previous.scrolling {
+                continue;
+            }
+            let (collected, scrolling) = self.collect(entries, None).await;
+            *section = Some(collected);
+            previous.scrolling = scrolling;
+        }
+
+        if let Some(width) = layout.width {
+            if scrolled {
+                // the width of scrolling modules does not change, so the
+                // reductions of the last full render still fit
+                let reductions = layout.reductions.lock().unwrap().clone();
+                for section in sections.iter_mut().flatten() {
+                    self.apply_reductions(section, &reductions).await;
+                }
+            } else {
+                let mut full = sections.map(Option::unwrap_or_default);
+                *layout.reductions.lock().unwrap() = self.fit_width(layout, &mut full, width, None).await;
+                sections = full.map(Some);
+            }
+        }
+
+        for ((section, previous), style) in sections.into_iter().zip(&mut rendered).zip(&layout.styles) {
+            if let Some(section) = section {
+                previous.text = match layout.frontend.as_str() {
+                    "lemonbar" => self.construct_lemonbar_section(section, style),
+                    _ => self.construct_generic_section(section, style),
+                };
+            }
+        }
+        *layout.rendered.lock().unwrap() = rendered.clone();
+
+        let [left, center, right] = rendered.map(|section| section.text);
+        match layout.frontend.as_str() {
+            // alignement in lemonbar is done with %{l}, %{c} and %{r}
+            "lemonbar" => format!("%{{l}}{left}%{{c}}{center}%{{r}}{right}"),
+            _ => [left, center, right].join("   "),
+        }
+    }
+
+    /// Render the bar in a `%{Sn}` block for every monitor, with the
+    /// modules placed on it. Monitors are all drawn again on marquee
+    /// steps, as there are usually few of them.
+    async fn render_monitors(&self, layout: &Layout, monitors: &[Monitor]) -> String {
+        let mut frame = String::new();
+        let mut scrolling = [false; 3];
+        for monitor in monitors {
+            let mut sections: [Section; 3] = Default::default();
+            for ((section, scrolls), entries) in sections.iter_mut().zip(&mut scrolling).zip(&layout.sections) {
+                let (collected, scrolled) = self.collect(entries, Some(monitor)).await;
+                *section = collected;
+                *scrolls |= scrolled;
+            }
+            if let Some(width) = layout.width {
+                self.fit_width(layout, &mut sections, width, Some(monitor)).await;
+            }
+
+            let texts: Vec<String> = sections
+                .into_iter()
+                .zip(&layout.styles)
+                .map(|(section, style)| self.construct_lemonbar_section(section, style))
+                .collect();
+            frame.push_str(&format!(
+                "%{{S{}}}%{{l}}{}%{{c}}{}%{{r}}{}",
+                monitor.index, texts[0], texts[1], texts[2]
+            ));
+        }
+
+        for (section, scrolls) in layout.rendered.lock().unwrap().iter_mut().zip(scrolling) {
+            section.scrolling = scrolls;
+        }
+        frame
+    }
+
+    /// Shorten or drop modules, lowest priority first, until the bar is
+    /// no wider than `width`, and return what was done to each
+    async fn fit_width(
         &self,
-    ) -> (Vec<ModuleOutput>, Vec<ModuleOutput>, Vec<ModuleOutput>) {
-        async fn collect(modules: &[Arc<dyn Module + Send + Sync>]) -> Vec<ModuleOutput> {
-            let mut results = Vec::with_capacity(modules.len());
-            for m in modules {
-                results.push(m.get_value().await);
+        layout: &Layout,
+        sections: &mut [Section<'_>; 3],
+        width: usize,
+        monitor: Option<&Monitor>,
+    ) -> HashMap<String, Reduction> {
+        let mut reductions = HashMap::new();
+        // among equal priorities, the rightmost module goes first
+        let mut order: Vec<&ModuleEntry> = sections.iter().flatten().map(|(entry, _)| *entry).rev().collect();
+        order.sort_by_key(|entry| entry.common.priority);
+
+        for entry in order {
+            if self.frame_width(layout, sections) <= width {
+                break;
+            }
+            let Some((s, i)) = find_in_sections(sections, entry) else {
+                continue;
+            };
+
+            let mut reduction = Reduction::default();
+            if let Some(short) = self.short_output(entry, monitor).await
+                && module_width(entry, &short) < module_width(entry, &sections[s][i].1)
+            {
+                sections[s][i].1 = short;
+                reduction.short = true;
+            }
+
+            let over = self.frame_width(layout, sections).saturating_sub(width);
+            if over > 0
+                && let Some(min_width) = entry.common.min_width
+            {
+                let value_width = width::display_width(&sections[s][i].1.value);
+                let columns = value_width.saturating_sub(over).max(min_width);
+                if columns < value_width {
+                    let output = &mut sections[s][i].1;
+                    output.value = width::truncate_columns(&output.value, columns);
+                    reduction.columns = Some(columns);
+                }
+            }
+
+            if self.frame_width(layout, sections) > width {
+                sections[s].remove(i);
+                reduction.dropped = true;
+            }
+            reductions.insert(entry.name.clone(), reduction);
+        }
+        reductions
+    }
+
+    /// Shorten or drop the modules of a section the way the last full
+    /// render did
+    async fn apply_reductions(&self, section: &mut Section<'_>, reductions: &HashMap<String, Reduction>) {
+        let mut kept = Vec::with_capacity(section.len());
+        for (entry, mut output) in section.drain(..) {
+            let Some(reduction) = reductions.get(&entry.name) else {
+                kept.push((entry, output));
+                continue;
+            };
+            if reduction.dropped {
+                continue;
+            }
+            if reduction.short
+                && let Some(short) = self.short_output(entry, None).await
+            {
+                output = short;
             }
-            results
+            if let Some(columns) = reduction.columns {
+                output.value = width::truncate_columns(&output.value, columns);
+            }
+            kept.push((entry, output));
         }
+        *section = kept;
+    }
 
-        let left = collect(&self.left).await;
-        let center = collect(&self.center).await;
-        let right = collect(&self.right).await;
+    /// Output of the short version of a module, styled like its output
+    async fn short_output(&self, entry: &ModuleEntry, monitor: Option<&Monitor>) -> Option<ModuleOutput> {
+        let output = match &entry.short {
+            Some(short) => value_on(short, monitor).await,
+            None => entry.module.get_short_value().await?,
+        };
+        let mut output = self.style(entry, output);
+        output.value = self.fit(entry, output.value);
+        Some(output)
+    }
 
-        (left, center, right)
+    /// Columns the sections take on the bar
+    fn frame_width(&self, layout: &Layout, sections: &[Section<'_>; 3]) -> usize {
+        let width: usize = sections
+            .iter()
+            .zip(&layout.styles)
+            .map(|(section, style)| {
+                let modules: Vec<usize> = section.iter().map(|(entry, m)| module_width(entry, m)).collect();
+                style.width(&modules, &group_separators(section))
+            })
+            .sum();
+        match layout.frontend.as_str() {
+            "lemonbar" => width,
+            // sections are joined by three spaces
+            _ => width + 6,
+        }
     }
 
-    fn construct_lemonbar_module(&self, m: &ModuleOutput) -> String {
-        let safe_value = m.value.replace('%', "%%");
+    /// Outputs of the visible modules of a section, on `monitor` when
+    /// the bar is drawn per monitor, and whether any of them scrolls
+    async fn collect<'a>(&self, entries: &'a [ModuleEntry], monitor: Option<&Monitor>) -> (Section<'a>, bool) {
+        let mut results = Vec::with_capacity(entries.len());
+        let mut scrolling = false;
+        let visible = |entry: &&ModuleEntry| !self.hidden.contains(&entry.name) && entry.shown_on(monitor);
+        // expanded groups are followed by their modules
+        let entries = entries.iter().filter(visible).flat_map(|entry| {
+            let expanded = entry.drawer.as_ref().is_some_and(|drawer| drawer.expanded());
+            let members = if expanded { entry.members.as_slice() } else { &[] };
+            std::iter::once(entry).chain(members.iter().filter(visible))
+        });
+        for entry in entries {
+            let mut output = self.style(entry, value_on(&entry.module, monitor).await);
+            // leaving hidden modules out here keeps separators from
+            // doubling up where they would have been
+            if !entry.hides(&output) {
+                scrolling |= self.scrolls(entry, &output.value);
+                output.value = self.fit(entry, output.value);
+                results.push((entry, output));
+            }
+        }
+        (results, scrolling)
+    }
+
+    fn style(&self, entry: &ModuleEntry, output: ModuleOutput) -> ModuleOutput {
+        let mut output = self.apply_error_style(entry, output);
+        output.icon_color = output.icon_color.and_then(|color| self.palette.resolve(color));
+        output.color = output.color.and_then(|color| self.palette.resolve(color));
+        output
+    }
+
+    /// Shorten a value to the module's `max_width`
+    fn fit(&self, entry: &ModuleEntry, value: String) -> String {
+        let Some(max_width) = entry.common.max_width else {
+            return value;
+        };
+        match entry.common.overflow {
+            Overflow::Ellipsis => width::truncate(&value, max_width),
+            Overflow::Marquee => {
+                let speed = entry.common.marquee_speed.unwrap_or(DEFAULT_MARQUEE_SPEED);
+                let offset = self.started.elapsed().as_secs_f64() * speed as f64;
+                width::marquee(&value, max_width, offset as usize)
+            }
+        }
+    }
+
+    fn scrolls(&self, entry: &ModuleEntry, value: &str) -> bool {
+        entry.common.overflow == Overflow::Marquee
+            && entry.common.max_width.is_some_and(|max_width| width::width(value) > max_width)
+    }
+
+    /// Replace the value of a failed module with the configured error
+    /// format, so every failure is rendered the same way
+    fn apply_error_style(&self, entry: &ModuleEntry, mut output: ModuleOutput) -> ModuleOutput {
+        if let Some(error) = &output.error {
+            let format = entry.common.error_format.as_deref().unwrap_or(&self.error_format);
+            output.value = placeholders::fill(format, |name| match name {
+                "error" => Some(error.message()),
+                "kind" => Some(error.kind()),
+                "module" => Some(&entry.name),
+                _ => None,
+            });
+            output.color = entry.common.error_color.clone().or_else(|| self.error_color.clone());
+        }
+        output
+    }
+
+    /// Background of a module, looked up in the palette
+    fn background(&self, entry: &ModuleEntry) -> Option<String> {
+        entry.common.background_color.clone().and_then(|color| self.palette.resolve(color))
+    }
+
+    fn construct_lemonbar_module(&self, entry: &ModuleEntry, m: &ModuleOutput) -> String {
+        let padding = " ".repeat(entry.common.padding);
+        let mut module = format!("{padding}{}{padding}", self.construct_lemonbar_content(m));
+        if m.urgent {
+            module = format!("%{{R}}{module}%{{R}}");
+        }
+        let buttons = entry.module.buttons();
+        if !buttons.is_empty() {
+            module = self.construct_lemonbar_clickable(&entry.name, &buttons, module);
+        }
+        if let Some(background) = self.background(entry) {
+            module = format!("%{{B{background}}}{module}%{{B-}}");
+        }
+        module
+    }
+
+    /// Wrap a module in click areas for the mouse buttons it reacts to,
+    /// whose command is printed by lemonbar when clicked, e.g.
+    /// `barrs -i default click volume 1`
+    fn construct_lemonbar_clickable(&self, name: &str, buttons: &[u8], module: String) -> String {
+        let escape = |s: &str| s.replace('%', "%%").replace(':', "\\:");
+        let (instance, name) = (escape(&self.instance), escape(name));
+        let mut clickable = String::new();
+        for button in buttons {
+            clickable.push_str(&format!("%{{A{button}:barrs -i {instance} click {name} {button}:}}"));
+        }
+        clickable.push_str(&module);
+        clickable.push_str(&"%{A}".repeat(buttons.len()));
+        clickable
+    }
+
+    fn construct_lemonbar_content(&self, m: &ModuleOutput) -> String {
+        let safe_value = match &m.color {
+            Some(color) => format!("%{{F{}}}{}%{{F-}}", color, m.value.replace('%', "%%")),
+            None => m.value.replace('%', "%%"),
+        };
         match (&m.icon, &m.icon_color) {
             (Some(icon), Some(color)) => {
                 let safe_icon = icon.replace('%', "%%");
@@ -112,49 +702,91 @@ impl Bar {
                 format!("{}{}", safe_icon, safe_value)
             }
             (None, _) => {
-                safe_value.clone()
+                safe_value
             }
         }
     }
 
-    fn construct_lemonbar_section(&self, section: Vec<ModuleOutput>) -> String {
-        section
-            .iter()
-            .map(|m| self.construct_lemonbar_module(m))
-            .collect::<Vec<String>>()
-            .join(&self.separator)
+    fn construct_lemonbar_section(&self, section: Section, style: &SectionStyle) -> String {
+        let backgrounds: Vec<Option<String>> = section.iter().map(|(entry, _)| self.background(entry)).collect();
+        style
+            .layout(&backgrounds, &group_separators(&section))
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Module(i) => self.construct_lemonbar_module(section[i].0, &section[i].1),
+                Piece::Glue { text, fg, bg } => {
+                    let mut glue = text.replace('%', "%%");
+                    if let Some(fg) = fg {
+                        glue = format!("%{{F{fg}}}{glue}%{{F-}}");
+                    }
+                    if let Some(bg) = bg {
+                        glue = format!("%{{B{bg}}}{glue}%{{B-}}");
+                    }
+                    glue
+                }
+            })
+            .collect()
     }
 
-    async fn construct_lemonbar(&self) -> String {
-        let (left, center, right) = self.collect_sections().await;
+    /// Lay a section out as plain text, without colors
+    fn construct_generic_section(&self, section: Section, style: &SectionStyle) -> String {
+        style
+            .layout(&vec![None; section.len()], &group_separators(&section))
+            .into_iter()
+            .map(|piece| match piece {
+                Piece::Module(i) => {
+                    let padding = " ".repeat(section[i].0.common.padding);
+                    format!("{padding}{}{padding}", section[i].1.value)
+                }
+                Piece::Glue { text, .. } => text.to_string(),
+            })
+            .collect()
+    }
+}
 
-        format!(
-            // alignement in lemonbar is done with %{l}, %{c} and %{r}
-            "%{{l}}{}%{{c}}{}%{{r}}{}",
-            self.construct_lemonbar_section(left),
-            self.construct_lemonbar_section(center),
-            self.construct_lemonbar_section(right),
-        )
+/// Output of a module, on `monitor` when the bar is drawn per monitor
+async fn value_on(module: &Arc<dyn Module + Send + Sync>, monitor: Option<&Monitor>) -> ModuleOutput {
+    match monitor {
+        Some(monitor) => module.get_value_on(monitor).await,
+        None => module.get_value().await,
     }
+}
 
-    async fn construct_generic(&self) -> String {
-        let (left, center, right) = self.collect_sections().await;
-        [
-            left
-                .iter()
-                .map(|m| m.value.clone())
-                .collect::<Vec<String>>()
-                .join(&self.separator),
-            center
-                .iter()
-                .map(|m| m.value.clone())
-                .collect::<Vec<String>>()
-                .join(&self.separator),
-            right
-                .iter()
-                .map(|m| m.value.clone())
-                .collect::<Vec<String>>()
-                .join(&self.separator),
-        ].join("   ")
+/// Time between marquee steps of the fastest module with `overflow =
+/// "marquee"`, if any
+fn marquee_step<'a>(entries: impl Iterator<Item = &'a ModuleEntry>) -> Option<Duration> {
+    entries
+        .filter(|entry| entry.common.max_width.is_some() && entry.common.overflow == Overflow::Marquee)
+        .map(|entry| entry.common.marquee_speed.unwrap_or(DEFAULT_MARQUEE_SPEED).max(1))
+        .max()
+        .map(|speed| Duration::from_millis((1000 / speed as u64).max(1)))
+}
+
+/// Columns a module takes on the bar, with its icon and padding
+fn module_width(entry: &ModuleEntry, m: &ModuleOutput) -> usize {
+    m.icon.as_deref().map_or(0, width::display_width) + width::display_width(&m.value) + 2 * entry.common.padding
+}
+
+/// Section and position of a module
+fn find_in_sections(sections: &[Section<'_>; 3], entry: &ModuleEntry) -> Option<(usize, usize)> {
+    sections.iter().enumerate().find_map(|(s, section)| {
+        section
+            .iter()
+            .position(|(other, _)| std::ptr::eq(*other, entry))
+            .map(|i| (s, i))
+    })
+}
+
+/// Separators replacing the section's separator before each module,
+/// which are those of groups between modules of the same group
+fn group_separators<'a>(section: &Section<'a>) -> Vec<Option<&'a str>> {
+    let mut separators = vec![None];
+    for pair in section.windows(2) {
+        let (previous, entry) = (pair[0].0, pair[1].0);
+        separators.push(match &entry.drawer {
+            Some(drawer) if entry.shares_drawer(previous) => drawer.separator.as_deref(),
+            _ => None,
+        });
     }
+    separators
 }
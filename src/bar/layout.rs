@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::config::{BarConfig, OutputConfig};
+use crate::Config;
+use super::{ModuleEntry, SectionStyle};
+
+/// The sections of one bar, how they are drawn and where
+pub struct Layout {
+    /// Name of the bar under `[bars]`, `None` for the top-level one
+    pub name: Option<String>,
+    pub sections: [Vec<ModuleEntry>; 3],
+    pub styles: [SectionStyle; 3],
+    pub frontend: String,
+    pub width: Option<usize>,
+    pub output: OutputConfig,
+    /// Draw the sections once per monitor
+    pub per_monitor: bool,
+    /// How modules were fitted into `width` by the last full render
+    pub reductions: Mutex<HashMap<String, Reduction>>,
+    pub rendered: Mutex<[RenderedSection; 3]>,
+}
+
+/// Last rendering of a section
+#[derive(Default, Clone)]
+pub struct RenderedSection {
+    pub text: String,
+    /// Whether a module of the section scrolls, so it has to be
+    /// rendered again on every marquee step
+    pub scrolling: bool,
+}
+
+/// How a module was fitted into the width of the bar
+#[derive(Default, Clone, Copy)]
+pub struct Reduction {
+    /// Shown with its `format_short*` formats
+    pub short: bool,
+    /// Value cut to this many columns
+    pub columns: Option<usize>,
+    pub dropped: bool,
+}
+
+impl Layout {
+    /// Lay out a bar, taking what it does not set from the top-level
+    /// keys of `config`
+    pub fn new(name: Option<&str>, bar: &BarConfig, config: &Config, sections: [Vec<ModuleEntry>; 3]) -> Self {
+        let separator = bar.separator.as_deref().unwrap_or(&config.separator);
+        let style = bar.sections.as_ref().unwrap_or(&config.sections);
+        Self {
+            name: name.map(str::to_string),
+            sections,
+            styles: [&style.left, &style.center, &style.right].map(|section| SectionStyle::new(section, separator)),
+            frontend: bar.frontend.clone().unwrap_or_else(|| config.frontend.clone()),
+            width: bar.width.or(config.width),
+            output: bar.output.clone(),
+            per_monitor: bar.per_monitor.unwrap_or(config.per_monitor),
+            reductions: Mutex::default(),
+            rendered: Mutex::default(),
+        }
+    }
+
+    /// Whether a section scrolled when last rendered
+    pub fn scrolling(&self) -> bool {
+        self.rendered.lock().unwrap().iter().any(|section| section.scrolling)
+    }
+
+    /// Name of a section as reported by `list`, prefixed with the name
+    /// of the bar, e.g. `bottom:left`
+    pub fn section_name(&self, section: &str) -> String {
+        match &self.name {
+            Some(name) => format!("{name}:{section}"),
+            None => section.to_string(),
+        }
+    }
+}
@@ -1,5 +1,31 @@
 mod modules;
-use modules::build_modules;
+use modules::{Built, ModuleEntry, Registry, build_modules};
 
 mod core;
-pub use core::Bar;
+pub use core::{Bar, ModuleInfo};
+
+mod layout;
+use layout::{Layout, Reduction};
+
+mod output;
+pub use output::{Frame, Output};
+
+mod builder;
+pub use builder::BarBuilder;
+
+mod monitors;
+pub use monitors::Monitor;
+use monitors::Monitors;
+
+mod palette;
+use palette::Palette;
+
+mod placeholders;
+
+mod style;
+use style::{Piece, SectionStyle};
+
+mod width;
+
+mod xevents;
+use xevents::XEvents;
@@ -1,17 +1,18 @@
 use async_trait::async_trait;
 use std::fs;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration};
 use crate::config::BatteryConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display battery info using a configured format
 #[derive(Debug)]
 pub struct BatteryModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_battery: Mutex<String>,
+    /// Formatted value and lowercase status of the battery
+    current_battery: Mutex<Result<(String, String), ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     name: String,
@@ -21,7 +22,7 @@ pub struct BatteryModule {
 }
 
 impl BatteryModule {
-    pub fn new(config: &BatteryConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &BatteryConfig, tx: UnboundedSender<()>) -> Self {
         let interval = config.interval;
         let name = config.name.clone();
         let format_charging = config.format_charging.clone();
@@ -48,26 +49,37 @@ impl BatteryModule {
 
 #[async_trait]
 impl Module for BatteryModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            {
-                *self.current_battery.lock().await = battery_from_string(
-                    &self.name,
-                    &self.format_charging,
-                    &self.format_discharging,
-                    &self.format_full,
-                );
-            }
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_battery.lock().await = battery_from_string(
+            &self.name,
+            &self.format_charging,
+            &self.format_discharging,
+            &self.format_full,
+        );
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
+        let current_battery = self.current_battery.lock().await.clone();
+        let state = current_battery.as_ref().ok().map(|(_, state)| state.clone());
         ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_battery.lock().await.clone(),
+            state,
+            ..ModuleOutput::from_result(
+                self.icon.clone(),
+                self.icon_color.clone(),
+                current_battery.map(|(value, _)| value),
+            )
         }
     }
 }
@@ -81,10 +93,11 @@ fn battery_from_string(
     format_charging: &str,
     format_discharging: &str,
     format_full: &str,
-) -> String {
+) -> Result<(String, String), ModuleError> {
     let base = format!("/sys/class/power_supply/{name}");
 
-    let status = read_battery_file(&format!("{base}/status")).unwrap_or_else(|| "Unknown".into());
+    let status = read_battery_file(&format!("{base}/status"))
+        .ok_or_else(|| ModuleError::Unavailable(format!("battery not found: {name}")))?;
     let capacity = read_battery_file(&format!("{base}/capacity")).unwrap_or_else(|| "0".into());
 
     let energy_now = read_battery_file(&format!("{base}/energy_now"))
@@ -131,11 +144,12 @@ fn battery_from_string(
         "Discharging" => format_discharging,
         "Not charging" => format_discharging,
         "Full" => format_full,
-        _ => "error: unknown battery status: {status}",
+        _ => return Err(ModuleError::Parse(format!("unknown battery status: {status}"))),
     };
 
-    template
+    let value = template
         .replace("{%}", &capacity)
         .replace("{status}", &status)
-        .replace("{time}", &time_str)
+        .replace("{time}", &time_str);
+    Ok((value, status.to_lowercase()))
 }
@@ -1,16 +1,16 @@
 use async_trait::async_trait;
 use std::process::Command;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use crate::config::BrightnessctlConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display brightness info about a given device using a configured format
 #[derive(Debug)]
 pub struct BrightnessctlModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     signal_id: Option<u8>,
-    current_brightness: Mutex<String>,
+    current_brightness: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     device_name: String,
@@ -18,7 +18,7 @@ pub struct BrightnessctlModule {
 }
 
 impl BrightnessctlModule {
-    pub fn new(config: &BrightnessctlConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &BrightnessctlConfig, tx: UnboundedSender<()>) -> Self {
         let device_name = config.device_name.clone();
         let format = config.format.clone();
         Self {
@@ -43,6 +43,10 @@ impl Module for BrightnessctlModule {
     }
 
     async fn run(&self) {
+        self.update().await;
+    }
+
+    async fn update(&self) {
         *self.current_brightness.lock().await = brightness_from_string(
             &self.device_name,
             &self.format,
@@ -51,23 +55,26 @@ impl Module for BrightnessctlModule {
     }
 
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_brightness.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_brightness.lock().await.clone(),
+        )
     }
 }
 
-fn brightness_from_string(device_name: &str, format: &str) -> String {
+fn brightness_from_string(device_name: &str, format: &str) -> Result<String, ModuleError> {
     let output = Command::new("brightnessctl")
         .args(["i", "-d", device_name])
         .output();
 
     let info = match output {
-        Ok(out) => String::from_utf8_lossy(&out.stdout).into_owned(),
+        Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).into_owned(),
+        Ok(_) => {
+            return Err(ModuleError::Unavailable(format!("brightness device not found: {device_name}")));
+        }
         Err(e) => {
-            return format!("error: failed to execute brightnessctl: {}", e);
+            return Err(ModuleError::Command(format!("failed to execute brightnessctl: {}", e)));
         }
     };
 
@@ -88,7 +95,7 @@ fn brightness_from_string(device_name: &str, format: &str) -> String {
     }
 
     match current_percent {
-        Some(value) => format.replace("{%}", value),
-        None => "error: could not parse current brightness".to_string(),
+        Some(value) => Ok(format.replace("{%}", value)),
+        None => Err(ModuleError::Parse("could not parse current brightness".to_string())),
     }
 }
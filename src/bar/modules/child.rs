@@ -0,0 +1,55 @@
+use std::io;
+use std::process::Stdio;
+use tokio::process::{Child, Command};
+use tokio::time::{Duration, Instant};
+
+const MIN_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// Start `exec` with `shell -c`, reading its stdout
+///
+/// The child is killed when dropped, so aborting the module task also
+/// stops its process.
+pub fn spawn_shell(shell: &str, exec: &str, stdin: Stdio) -> io::Result<Child> {
+    Command::new(shell)
+        .arg("-c")
+        .arg(exec)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+}
+
+/// Exponential delay between restarts of a child process
+///
+/// The delay doubles on every restart and goes back to the minimum once
+/// the child managed to stay up longer than the maximum delay.
+pub struct Backoff {
+    delay: Duration,
+    started: Instant,
+}
+
+impl Backoff {
+    pub fn new() -> Self {
+        Self {
+            delay: MIN_RESTART_DELAY,
+            started: Instant::now(),
+        }
+    }
+
+    /// Record that the child was (re)started
+    pub fn start(&mut self) {
+        self.started = Instant::now();
+    }
+
+    /// Delay to wait before the next restart
+    pub fn next_delay(&mut self) -> Duration {
+        if self.started.elapsed() > MAX_RESTART_DELAY {
+            self.delay = MIN_RESTART_DELAY;
+        }
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(MAX_RESTART_DELAY);
+        delay
+    }
+}
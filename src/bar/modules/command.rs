@@ -0,0 +1,180 @@
+use async_trait::async_trait;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::process::Command;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, sleep, timeout};
+use crate::bar::placeholders;
+use crate::config::CommandConfig;
+use crate::{Module, ModuleError, ModuleOutput};
+use super::Formats;
+
+/// What the command printed, in each format
+#[derive(Debug, Clone)]
+struct Printed {
+    values: Formats<String>,
+    color: Option<String>,
+}
+
+/// Display the output of a shell command
+#[derive(Debug)]
+pub struct CommandModule {
+    tx: UnboundedSender<()>,
+    interval: Option<u64>,
+    signal_id: Option<u8>,
+    output: Mutex<Result<Printed, ModuleError>>,
+    exec: String,
+    shell: String,
+    timeout: Option<u64>,
+    icon: Option<String>,
+    icon_color: Option<String>,
+    /// `format` and `format_failed` of each version
+    formats: Formats<(String, Option<String>)>,
+    showing_alt: AtomicBool,
+    color: Option<String>,
+    color_failed: Option<String>,
+}
+
+impl CommandModule {
+    pub fn new(config: &CommandConfig, formats: Formats<(String, Option<String>)>, tx: UnboundedSender<()>) -> Self {
+        Self {
+            tx,
+            interval: config.interval,
+            signal_id: config.signal_id,
+            output: Mutex::new(Ok(Printed {
+                values: formats.map(|_| String::new()),
+                color: None,
+            })),
+            exec: config.exec.clone(),
+            shell: config.shell.clone(),
+            timeout: config.timeout,
+            icon: config.icon.clone(),
+            icon_color: config.icon_color.clone(),
+            formats,
+            showing_alt: AtomicBool::new(false),
+            color: config.color.clone(),
+            color_failed: config.color_failed.clone(),
+        }
+    }
+
+    async fn execute(&self) -> Result<Printed, ModuleError> {
+        let child = Command::new(&self.shell)
+            .arg("-c")
+            .arg(&self.exec)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .output();
+
+        let output = match self.timeout {
+            Some(secs) => timeout(Duration::from_secs(secs), child).await.map_err(|_| {
+                ModuleError::Command(format!("{} timed out after {secs}s", self.exec))
+            })?,
+            None => child.await,
+        };
+        let output = output
+            .map_err(|e| ModuleError::Command(format!("failed to execute {}: {e}", self.shell)))?;
+
+        // only the first line fits on the bar
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = stdout.lines().next().unwrap_or("").trim_end();
+        let exit_code = output
+            .status
+            .code()
+            .map_or_else(|| "-".to_string(), |code| code.to_string());
+
+        let success = output.status.success();
+        let color = if success || self.color_failed.is_none() { &self.color } else { &self.color_failed };
+
+        let values = self.formats.map(|(format, format_failed)| {
+            let format = if success { format } else { format_failed.as_ref().unwrap_or(format) };
+            placeholders::fill(format, |name| match name {
+                "stdout" => Some(stdout),
+                "exit_code" => Some(&exit_code),
+                _ => None,
+            })
+        });
+        Ok(Printed {
+            values,
+            color: color.clone(),
+        })
+    }
+
+    async fn output_for(&self, short: bool) -> ModuleOutput {
+        match self.output.lock().await.clone() {
+            Ok(printed) => ModuleOutput {
+                icon: self.icon.clone(),
+                icon_color: self.icon_color.clone(),
+                value: printed.values.shown(self.showing_alt.load(Ordering::SeqCst), short).clone(),
+                color: printed.color,
+                ..Default::default()
+            },
+            Err(error) => ModuleOutput::from_result(
+                self.icon.clone(),
+                self.icon_color.clone(),
+                Err(error),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl Module for CommandModule {
+    fn interval(&self) -> Option<u64> {
+        self.interval
+    }
+
+    fn signal_id(&self) -> Option<u8> {
+        self.signal_id
+    }
+
+    async fn run(&self) {
+        let Some(interval) = self.interval else {
+            self.update().await;
+            return;
+        };
+        loop {
+            self.update().await;
+            sleep(Duration::from_secs(interval)).await;
+        }
+    }
+
+    async fn update(&self) {
+        *self.output.lock().await = self.execute().await;
+        let _ = self.tx.send(());
+    }
+
+    async fn get_value(&self) -> ModuleOutput {
+        self.output_for(false).await
+    }
+
+    async fn get_short_value(&self) -> Option<ModuleOutput> {
+        self.formats.short.as_ref()?;
+        Some(self.output_for(true).await)
+    }
+
+    fn switches_formats(&self) -> bool {
+        true
+    }
+
+    fn toggle(&self) -> bool {
+        if self.formats.alt.is_none() {
+            return false;
+        }
+        self.showing_alt.fetch_xor(true, Ordering::SeqCst);
+        let _ = self.tx.send(());
+        true
+    }
+
+    fn buttons(&self) -> Vec<u8> {
+        if self.formats.alt.is_some() { vec![1] } else { Vec::new() }
+    }
+
+    /// Toggle on a left click
+    async fn click(&self, button: u8) {
+        if button == 1 {
+            self.toggle();
+        }
+    }
+}
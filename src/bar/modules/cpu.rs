@@ -3,13 +3,13 @@ use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use std::fs;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use crate::config::CpuConfig;
 use crate::{Module, ModuleOutput};
 
 #[derive(Debug)]
 pub struct CpuModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
     current_usage: Mutex<String>,
     icon: Option<String>,
@@ -20,7 +20,7 @@ pub struct CpuModule {
 }
 
 impl CpuModule {
-    pub fn new(config: &CpuConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &CpuConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         let (total, idle) = read_cpu_jiffies().unwrap_or((0, 0));
 
@@ -39,31 +39,40 @@ impl CpuModule {
 
 #[async_trait]
 impl Module for CpuModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            if let Some((total, idle)) = read_cpu_jiffies() {
-                *self.current_usage.lock().await = calculate_usage(
-                    &self.format,
-                    self.prev_total.load(Ordering::SeqCst),
-                    self.prev_idle.load(Ordering::SeqCst),
-                    total,
-                    idle,
-                );
-
-                // Save snapshot for next update
-                self.prev_total.store(total, Ordering::SeqCst);
-                self.prev_idle.store(idle, Ordering::SeqCst);
-            }
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        if let Some((total, idle)) = read_cpu_jiffies() {
+            *self.current_usage.lock().await = calculate_usage(
+                &self.format,
+                self.prev_total.load(Ordering::SeqCst),
+                self.prev_idle.load(Ordering::SeqCst),
+                total,
+                idle,
+            );
+
+            // Save snapshot for next update
+            self.prev_total.store(total, Ordering::SeqCst);
+            self.prev_idle.store(idle, Ordering::SeqCst);
+        }
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
         ModuleOutput {
             icon: self.icon.clone(),
             icon_color: self.icon_color.clone(),
             value: self.current_usage.lock().await.clone(),
+            ..Default::default()
         }
     }
 }
@@ -3,23 +3,23 @@ use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use std::fs;
 use std::path::Path;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use crate::config::CputempConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display temperature of CPU using a configured format
 #[derive(Debug)]
 pub struct CputempModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_temp: Mutex<String>,
+    current_temp: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format: String,
 }
 
 impl CputempModule {
-    pub fn new(config: &CputempConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &CputempConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         Self {
             tx,
@@ -34,24 +34,32 @@ impl CputempModule {
 
 #[async_trait]
 impl Module for CputempModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.current_temp.lock().await = cputemp_from_string(&self.format);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_temp.lock().await = cputemp_from_string(&self.format);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_temp.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_temp.lock().await.clone(),
+        )
     }
 }
 
-fn cputemp_from_string(format: &str) -> String {
+fn cputemp_from_string(format: &str) -> Result<String, ModuleError> {
     fn read_cpu_temp_celsius() -> Option<f64> {
         let thermal_path = Path::new("/sys/class/thermal");
 
@@ -71,7 +79,7 @@ fn cputemp_from_string(format: &str) -> String {
 
     let celsius = match read_cpu_temp_celsius() {
         Some(c) => c,
-        None => return format.to_string(),
+        None => return Err(ModuleError::Unavailable("no thermal zone temperature found".to_string())),
     };
 
     let kelvin = celsius + 273.15;
@@ -81,8 +89,8 @@ fn cputemp_from_string(format: &str) -> String {
     let k = format!("{:.1}", kelvin);
     let f = format!("{:.1}", fahrenheit);
 
-    format
+    Ok(format
         .replace("{c}", &c)
         .replace("{k}", &k)
-        .replace("{f}", &f)
+        .replace("{f}", &f))
 }
@@ -2,14 +2,14 @@ use async_trait::async_trait;
 use chrono::Local;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use crate::config::DateConfig;
 use crate::{Module, ModuleOutput};
 
 /// Display date using a configured format
 #[derive(Debug)]
 pub struct DateModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
     current_date: Mutex<String>,
     icon: Option<String>,
@@ -18,7 +18,7 @@ pub struct DateModule {
 }
 
 impl DateModule {
-    pub fn new(config: &DateConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &DateConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         Self {
             tx,
@@ -33,19 +33,28 @@ impl DateModule {
 
 #[async_trait]
 impl Module for DateModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.current_date.lock().await = date_from_string(&self.format);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_date.lock().await = date_from_string(&self.format);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
         ModuleOutput {
             icon: self.icon.clone(),
             icon_color: self.icon_color.clone(),
             value: self.current_date.lock().await.clone(),
+            ..Default::default()
         }
     }
 }
@@ -0,0 +1,257 @@
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::ChildStdin;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use crate::bar::placeholders;
+use crate::config::ExternalConfig;
+use crate::{Module, ModuleError, ModuleOutput};
+use super::child::{Backoff, spawn_shell};
+use super::Formats;
+
+/// A line printed by an external module
+#[derive(Deserialize)]
+struct Update {
+    #[serde(default)]
+    text: String,
+    color: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    urgent: bool,
+    state: Option<String>,
+    #[serde(default)]
+    values: Map<String, Value>,
+    error: Option<String>,
+}
+
+/// What the module currently shows
+#[derive(Debug, Clone)]
+struct Display {
+    /// The text in each format
+    value: Formats<String>,
+    color: Option<String>,
+    icon: Option<String>,
+    urgent: bool,
+    state: Option<String>,
+}
+
+/// Display a module running as a separate process, talking JSON lines
+/// over its stdin and stdout
+#[derive(Debug)]
+pub struct ExternalModule {
+    tx: UnboundedSender<()>,
+    name: String,
+    config: Value,
+    events: UnboundedSender<Value>,
+    pending_events: Mutex<UnboundedReceiver<Value>>,
+    display: Mutex<Result<Display, ModuleError>>,
+    exec: String,
+    shell: String,
+    icon: Option<String>,
+    icon_color: Option<String>,
+    formats: Formats<Option<String>>,
+    showing_alt: AtomicBool,
+}
+
+impl ExternalModule {
+    pub fn new(
+        name: &str,
+        config: &ExternalConfig,
+        table: toml::Table,
+        formats: Formats<Option<String>>,
+        tx: UnboundedSender<()>,
+    ) -> Self {
+        let (events, pending_events) = mpsc::unbounded_channel();
+        Self {
+            tx,
+            name: name.to_string(),
+            config: serde_json::to_value(table).unwrap_or_default(),
+            events,
+            pending_events: Mutex::new(pending_events),
+            display: Mutex::new(Ok(Display {
+                value: formats.map(|_| String::new()),
+                color: None,
+                icon: None,
+                urgent: false,
+                state: None,
+            })),
+            exec: config.exec.clone(),
+            shell: config.shell.clone(),
+            icon: config.icon.clone(),
+            icon_color: config.icon_color.clone(),
+            formats,
+            showing_alt: AtomicBool::new(false),
+        }
+    }
+
+    async fn set_display(&self, display: Result<Display, ModuleError>) {
+        *self.display.lock().await = display;
+        let _ = self.tx.send(());
+    }
+
+    fn parse_update(&self, line: &str) -> Result<Display, ModuleError> {
+        let update: Update = serde_json::from_str(line)
+            .map_err(|e| ModuleError::Parse(format!("invalid update from {}: {e}", self.exec)))?;
+        if let Some(error) = update.error {
+            return Err(ModuleError::Command(error));
+        }
+
+        let values: HashMap<String, String> = update
+            .values
+            .into_iter()
+            .map(|(key, field)| match field {
+                Value::String(s) => (key, s),
+                field => (key, field.to_string()),
+            })
+            .collect();
+        let value = self.formats.map(|format| match format {
+            Some(format) => placeholders::fill(format, |name| match name {
+                "text" => Some(&update.text),
+                name => values.get(name).map(String::as_str),
+            }),
+            None => update.text.clone(),
+        });
+
+        Ok(Display {
+            value,
+            color: update.color,
+            icon: update.icon,
+            urgent: update.urgent,
+            state: update.state,
+        })
+    }
+
+    /// Run the child until it exits, forwarding events to it, and
+    /// return why it stopped
+    async fn session(&self, events: &mut UnboundedReceiver<Value>) -> ModuleError {
+        let mut child = match spawn_shell(&self.shell, &self.exec, Stdio::piped()) {
+            Ok(child) => child,
+            Err(e) => return ModuleError::Command(format!("failed to execute {}: {e}", self.shell)),
+        };
+        let (Some(mut stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return ModuleError::Command(format!("no pipes to {}", self.exec));
+        };
+
+        // events sent while the child was down are stale
+        while events.try_recv().is_ok() {}
+
+        let config = json!({ "event": "config", "name": self.name, "config": self.config });
+        let _ = send_event(&mut stdin, &config).await;
+
+        let mut lines = BufReader::new(stdout).lines();
+        loop {
+            tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) if line.trim().is_empty() => {}
+                    Ok(Some(line)) => self.set_display(self.parse_update(&line)).await,
+                    _ => break,
+                },
+                Some(event) = events.recv() => {
+                    let _ = send_event(&mut stdin, &event).await;
+                }
+            }
+        }
+
+        drop(stdin);
+        match child.wait().await {
+            Ok(status) => ModuleError::Command(format!("{} exited ({status})", self.exec)),
+            Err(e) => ModuleError::Command(format!("{} failed: {e}", self.exec)),
+        }
+    }
+
+    async fn output_for(&self, short: bool) -> ModuleOutput {
+        match self.display.lock().await.clone() {
+            Ok(display) => ModuleOutput {
+                icon: display.icon.or_else(|| self.icon.clone()),
+                icon_color: self.icon_color.clone(),
+                value: display.value.shown(self.showing_alt.load(Ordering::SeqCst), short).clone(),
+                color: display.color,
+                urgent: display.urgent,
+                state: display.state,
+                ..Default::default()
+            },
+            Err(error) => ModuleOutput::from_result(
+                self.icon.clone(),
+                self.icon_color.clone(),
+                Err(error),
+            ),
+        }
+    }
+}
+
+async fn send_event(stdin: &mut ChildStdin, event: &Value) -> std::io::Result<()> {
+    let mut line = event.to_string();
+    line.push('\n');
+    stdin.write_all(line.as_bytes()).await?;
+    stdin.flush().await
+}
+
+#[async_trait]
+impl Module for ExternalModule {
+    async fn run(&self) {
+        let mut events = self.pending_events.lock().await;
+        let mut backoff = Backoff::new();
+        loop {
+            backoff.start();
+            let error = self.session(&mut events).await;
+            let delay = backoff.next_delay();
+            self.set_display(Err(ModuleError::Command(format!(
+                "{error}, restarting in {}s",
+                delay.as_secs()
+            ))))
+            .await;
+            sleep(delay).await;
+        }
+    }
+
+    async fn update(&self) {
+        let _ = self.events.send(json!({ "event": "update" }));
+        let _ = self.tx.send(());
+    }
+
+    fn buttons(&self) -> Vec<u8> {
+        (1..=5).collect()
+    }
+
+    fn switches_formats(&self) -> bool {
+        true
+    }
+
+    fn toggle(&self) -> bool {
+        if self.formats.alt.is_none() {
+            return false;
+        }
+        self.showing_alt.fetch_xor(true, Ordering::SeqCst);
+        let _ = self.tx.send(());
+        true
+    }
+
+    /// Toggle on a left click with an alternate format, and send the
+    /// other buttons to the process
+    async fn click(&self, button: u8) {
+        if button == 1 && self.toggle() {
+            return;
+        }
+        let event = match button {
+            4 => json!({ "event": "scroll", "direction": "up" }),
+            5 => json!({ "event": "scroll", "direction": "down" }),
+            button => json!({ "event": "click", "button": button }),
+        };
+        let _ = self.events.send(event);
+    }
+
+    async fn get_value(&self) -> ModuleOutput {
+        self.output_for(false).await
+    }
+
+    async fn get_short_value(&self) -> Option<ModuleOutput> {
+        self.formats.short.as_ref()?;
+        Some(self.output_for(true).await)
+    }
+}
@@ -1,35 +1,256 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
 use std::sync::Arc;
-use std::sync::mpsc::Sender;
-use crate::config::ModuleConfig;
-use crate::Module;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::config::{
+    CommandConfig, CommonConfig, ExternalConfig, GroupConfig, ModuleConfig, SectionItem, StreamConfig, module_kind,
+};
+use crate::bar::Monitor;
+use crate::{Module, ModuleError, ModuleOutput};
 use super::*;
 
+/// A module instance together with the options the bar applies to it
+#[derive(Clone)]
+pub struct ModuleEntry {
+    pub name: String,
+    pub module: Arc<dyn Module + Send + Sync>,
+    /// The module built with its `format_short*` formats, shown instead
+    /// when the bar runs out of width
+    pub short: Option<Arc<dyn Module + Send + Sync>>,
+    pub common: CommonConfig,
+    /// The group the module is the summary of, or is part of
+    pub drawer: Option<Arc<Drawer>>,
+    /// Modules of the group, when this is a group
+    pub members: Vec<ModuleEntry>,
+}
+
+impl ModuleEntry {
+    /// Whether `target` refers to this module, either by its instance
+    /// name, its kind, one of its groups, or `all`
+    pub fn matches(&self, target: &str) -> bool {
+        target == "all"
+            || self.name == target
+            || module_kind(&self.name) == target
+            || self.common.groups.iter().any(|group| group == target)
+    }
+
+    /// The module and its short version, if any
+    pub fn modules(&self) -> impl Iterator<Item = &Arc<dyn Module + Send + Sync>> {
+        std::iter::once(&self.module).chain(&self.short)
+    }
+
+    /// Whether both modules are part of the same group, counting the
+    /// group itself
+    pub fn shares_drawer(&self, other: &ModuleEntry) -> bool {
+        match (&self.drawer, &other.drawer) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    /// Whether the module is placed on a monitor, or on the bar when it
+    /// is not drawn per monitor
+    pub fn shown_on(&self, monitor: Option<&Monitor>) -> bool {
+        let monitors = &self.common.monitors;
+        monitor.is_none_or(|monitor| monitors.is_empty() || monitors.iter().any(|name| monitor.is(name)))
+    }
+
+    /// Whether the output, styled with `error_format` for failures,
+    /// meets one of the `hide_when` conditions or is a failure shown as
+    /// nothing
+    pub fn hides(&self, output: &ModuleOutput) -> bool {
+        let hide_when = &self.common.hide_when;
+        if let Some(error) = &output.error
+            && (output.value.trim().is_empty() || hide_when.missing && matches!(error, ModuleError::Unavailable(_)))
+        {
+            return true;
+        }
+
+        (hide_when.empty && output.value.trim().is_empty())
+            || hide_when.matches.as_ref().is_some_and(|regex| regex.is_match(&output.value))
+            || hide_when.state.is_some() && output.state == hide_when.state
+    }
+}
+
+type Constructor = dyn Fn(&str, &ModuleConfig, &UnboundedSender<()>) -> Result<Arc<dyn Module + Send + Sync>>
+    + Send
+    + Sync;
+
+/// Modules registered on top of the built-in ones, keyed by kind
+#[derive(Default, Clone)]
+pub struct Registry {
+    constructors: HashMap<String, Arc<Constructor>>,
+}
+
+impl Registry {
+    pub fn register<C, M, F>(&mut self, kind: &str, constructor: F)
+    where
+        C: DeserializeOwned,
+        M: Module + 'static,
+        F: Fn(&str, &C, UnboundedSender<()>) -> M + Send + Sync + 'static,
+    {
+        let constructor = move |name: &str, config: &ModuleConfig, tx: &UnboundedSender<()>| {
+            Ok(Arc::new(constructor(name, &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>)
+        };
+        self.constructors.insert(kind.to_string(), Arc::new(constructor));
+    }
+}
+
+/// Entries already built, keyed by module or group name, so that every
+/// section and bar naming a module shares a single instance of it
+pub type Built = HashMap<String, ModuleEntry>;
+
 pub fn build_modules(
-    module_strings: &[String],
+    items: &[SectionItem],
     config: &ModuleConfig,
-    tx: &Sender<()>,
-) -> Vec<Arc<dyn Module + Send + Sync>> {
-    module_strings
+    registry: &Registry,
+    tx: &UnboundedSender<()>,
+    built: &mut Built,
+) -> Vec<ModuleEntry> {
+    items
         .iter()
-        .map(|s| {
-            match s.as_str() {
-                "battery" =>       Arc::new(BatteryModule::new(      &config.battery      , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "brightnessctl" => Arc::new(BrightnessctlModule::new(&config.brightnessctl, tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "cpu" =>           Arc::new(CpuModule::new(          &config.cpu          , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "cputemp" =>       Arc::new(CputempModule::new(      &config.cputemp      , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "date" =>          Arc::new(DateModule::new(         &config.date         , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "filesystem" =>    Arc::new(FilesystemModule::new(   &config.filesystem   , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "kernel" =>        Arc::new(KernelModule::new(       &config.kernel       , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "loadavg" =>       Arc::new(LoadavgModule::new(      &config.loadavg      , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "memory" =>        Arc::new(MemoryModule::new(       &config.memory       , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "network" =>       Arc::new(NetworkModule::new(      &config.network      , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "uptime" =>        Arc::new(UptimeModule::new(       &config.uptime       , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "wpctl" =>         Arc::new(WpctlModule::new(        &config.wpctl        , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "xkeyboard" =>     Arc::new(XkeyboardModule::new(    &config.xkeyboard    , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "xwindow" =>       Arc::new(XwindowModule::new(      &config.xwindow      , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                "xworkspaces" =>   Arc::new(XworkspacesModule::new(  &config.xworkspaces  , tx.clone())) as Arc<dyn Module + Send + Sync>,
-                _ =>               Arc::new(InvalidModule::new(      s                    , tx.clone())) as Arc<dyn Module + Send + Sync>,
-            }
+        .map(|item| match item {
+            SectionItem::Module(name) => build_entry(name, config, registry, tx, built),
+            SectionItem::Group(group) => build_group(group, config, registry, tx, built),
         })
         .collect()
 }
+
+fn build_entry(
+    name: &str,
+    config: &ModuleConfig,
+    registry: &Registry,
+    tx: &UnboundedSender<()>,
+    built: &mut Built,
+) -> ModuleEntry {
+    if let Some(entry) = built.get(name) {
+        return entry.clone();
+    }
+
+    let built_module = config.get::<CommonConfig>(name).and_then(|common| {
+        let module = build_toggleable(name, config, registry, tx)?;
+        let short = if module.switches_formats() {
+            None
+        } else {
+            config
+                .variant(name, "format_short")
+                .map(|short| build_module(name, &short, registry, tx))
+                .transpose()?
+        };
+        Ok((module, short, common))
+    });
+
+    let (module, short, common) = match built_module {
+        Ok(built) => built,
+        Err(e) => (
+            Arc::new(InvalidModule::new(ModuleError::Config(e.to_string()))) as Arc<dyn Module + Send + Sync>,
+            None,
+            CommonConfig::default(),
+        ),
+    };
+
+    let entry = ModuleEntry {
+        name: name.to_string(),
+        module,
+        short,
+        common,
+        drawer: None,
+        members: Vec::new(),
+    };
+    built.insert(name.to_string(), entry.clone());
+    entry
+}
+
+/// Build the summary entry of a group, holding the group's modules
+fn build_group(
+    group: &GroupConfig,
+    config: &ModuleConfig,
+    registry: &Registry,
+    tx: &UnboundedSender<()>,
+    built: &mut Built,
+) -> ModuleEntry {
+    if let Some(entry) = built.get(&group.group) {
+        return entry.clone();
+    }
+
+    let module = GroupModule::new(group, tx.clone());
+    let drawer = module.drawer();
+    let members = group
+        .modules
+        .iter()
+        .map(|name| ModuleEntry {
+            drawer: Some(Arc::clone(&drawer)),
+            ..build_entry(name, config, registry, tx, built)
+        })
+        .collect();
+
+    // the group is styled like a module, and hidden while expanded
+    // without a label
+    let mut common = config.get::<CommonConfig>(&group.group).unwrap_or_default();
+    common.hide_when.empty = true;
+
+    let entry = ModuleEntry {
+        name: group.group.clone(),
+        module: Arc::new(module),
+        short: None,
+        common,
+        drawer: Some(drawer),
+        members,
+    };
+    built.insert(group.group.clone(), entry.clone());
+    entry
+}
+
+/// Build a module, along with its alternate version when it sets any
+/// `format_alt*` key
+fn build_toggleable(
+    name: &str,
+    config: &ModuleConfig,
+    registry: &Registry,
+    tx: &UnboundedSender<()>,
+) -> Result<Arc<dyn Module + Send + Sync>> {
+    let module = build_module(name, config, registry, tx)?;
+    if module.switches_formats() {
+        return Ok(module);
+    }
+    let Some(alternate) = config.variant(name, "format_alt") else {
+        return Ok(module);
+    };
+    let alt = build_module(name, &alternate, registry, tx)?;
+    Ok(Arc::new(ToggleModule::new(module, alt, tx.clone())))
+}
+
+fn build_module(
+    name: &str,
+    config: &ModuleConfig,
+    registry: &Registry,
+    tx: &UnboundedSender<()>,
+) -> Result<Arc<dyn Module + Send + Sync>> {
+    if let Some(constructor) = registry.constructors.get(module_kind(name)) {
+        return constructor(name, config, tx);
+    }
+
+    Ok(match module_kind(name) {
+        "battery" =>       Arc::new(BatteryModule::new(      &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "brightnessctl" => Arc::new(BrightnessctlModule::new(&config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "command" =>       Arc::new(CommandModule::new(      &config.get(name)?, Formats::read(name, config, |c: CommandConfig| (c.format, c.format_failed))?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "cpu" =>           Arc::new(CpuModule::new(          &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "cputemp" =>       Arc::new(CputempModule::new(      &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "date" =>          Arc::new(DateModule::new(         &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "external" =>      Arc::new(ExternalModule::new(name, &config.get(name)?, config.table(name), Formats::read(name, config, |c: ExternalConfig| c.format)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "filesystem" =>    Arc::new(FilesystemModule::new(   &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "ipc" =>           Arc::new(IpcModule::new(    name, &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "kernel" =>        Arc::new(KernelModule::new(       &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "loadavg" =>       Arc::new(LoadavgModule::new(      &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "memory" =>        Arc::new(MemoryModule::new(       &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "network" =>       Arc::new(NetworkModule::new(      &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "stream" =>        Arc::new(StreamModule::new(       &config.get(name)?, Formats::read(name, config, |c: StreamConfig| c.format)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "uptime" =>        Arc::new(UptimeModule::new(       &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "wpctl" =>         Arc::new(WpctlModule::new(        &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "xkeyboard" =>     Arc::new(XkeyboardModule::new(    &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "xwindow" =>       Arc::new(XwindowModule::new(      &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        "xworkspaces" =>   Arc::new(XworkspacesModule::new(  &config.get(name)?, tx.clone())) as Arc<dyn Module + Send + Sync>,
+        _ => anyhow::bail!("invalid module name: {}", name),
+    })
+}
@@ -2,18 +2,18 @@ use async_trait::async_trait;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use std::ffi::CString;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use libc;
 use std::mem::MaybeUninit;
 use crate::config::FilesystemConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display information about the filesystem using a configured format
 #[derive(Debug)]
 pub struct FilesystemModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_fs_info: Mutex<String>,
+    current_fs_info: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format: String,
@@ -21,7 +21,7 @@ pub struct FilesystemModule {
 }
 
 impl FilesystemModule {
-    pub fn new(config: &FilesystemConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &FilesystemConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         let mountpoint = config.mountpoint.clone();
         Self {
@@ -38,20 +38,28 @@ impl FilesystemModule {
 
 #[async_trait]
 impl Module for FilesystemModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.current_fs_info.lock().await = fs_info_from_string(&self.format, &self.mountpoint);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_fs_info.lock().await = fs_info_from_string(&self.format, &self.mountpoint);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_fs_info.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_fs_info.lock().await.clone(),
+        )
     }
 }
 
@@ -61,10 +69,10 @@ struct FsStats {
     used: u64,
 }
 
-fn fs_info_from_string(format: &str, mountpoint: &str) -> String {
+fn fs_info_from_string(format: &str, mountpoint: &str) -> Result<String, ModuleError> {
     let stats = match read_fs_stats(mountpoint) {
         Some(s) => s,
-        None => return format.replace("{mount}", mountpoint),
+        None => return Err(ModuleError::Unavailable(format!("could not read filesystem at {mountpoint}"))),
     };
 
     let used_pct = if stats.total > 0 {
@@ -75,7 +83,7 @@ fn fs_info_from_string(format: &str, mountpoint: &str) -> String {
 
     let free_pct = 100.0 - used_pct;
 
-    format
+    Ok(format
         .replace("{mount}", mountpoint)
 
         // IEC totals
@@ -116,7 +124,7 @@ fn fs_info_from_string(format: &str, mountpoint: &str) -> String {
 
         // Percentages
         .replace("{%_used}", &format!("{:.1}", used_pct))
-        .replace("{%_free}", &format!("{:.1}", free_pct))
+        .replace("{%_free}", &format!("{:.1}", free_pct)))
 }
 
 fn kib(b: u64) -> u64 { b / 1024 }
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use crate::config::ModuleConfig;
+
+/// The formats of a module and of its `format_alt*` and
+/// `format_short*` versions, for modules that format what their command
+/// prints with each of them instead of being built, and running the
+/// command, once per version
+#[derive(Debug, Clone)]
+pub struct Formats<T> {
+    pub main: T,
+    pub alt: Option<T>,
+    pub short: Option<T>,
+}
+
+impl<T> Formats<T> {
+    /// Read the formats of a module instance from its configuration
+    /// and the configurations of its other versions, if any
+    pub fn read<C: DeserializeOwned>(name: &str, config: &ModuleConfig, get: impl Fn(C) -> T) -> Result<Self> {
+        let read = |config: &ModuleConfig| config.get::<C>(name).map(&get);
+        Ok(Self {
+            main: read(config)?,
+            alt: config.variant(name, "format_alt").map(|alt| read(&alt)).transpose()?,
+            short: config.variant(name, "format_short").map(|short| read(&short)).transpose()?,
+        })
+    }
+
+    pub fn map<U>(&self, f: impl Fn(&T) -> U) -> Formats<U> {
+        Formats {
+            main: f(&self.main),
+            alt: self.alt.as_ref().map(&f),
+            short: self.short.as_ref().map(&f),
+        }
+    }
+
+    /// The short version when `short` is set, or else the alternate
+    /// version while `showing_alt` is set, if there is one
+    pub fn shown(&self, showing_alt: bool, short: bool) -> &T {
+        match (&self.short, &self.alt) {
+            (Some(short_version), _) if short => short_version,
+            (_, Some(alt)) if showing_alt => alt,
+            _ => &self.main,
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc::UnboundedSender;
+use crate::config::GroupConfig;
+use crate::{Module, ModuleOutput};
+
+/// State of a group, shared by the group and its modules
+pub struct Drawer {
+    pub separator: Option<String>,
+    expanded: AtomicBool,
+}
+
+impl Drawer {
+    pub fn expanded(&self) -> bool {
+        self.expanded.load(Ordering::SeqCst)
+    }
+
+    pub fn set_expanded(&self, expanded: bool) {
+        self.expanded.store(expanded, Ordering::SeqCst);
+    }
+}
+
+/// The summary of a group of modules, which expands or collapses the
+/// group when toggled
+pub struct GroupModule {
+    tx: UnboundedSender<()>,
+    drawer: Arc<Drawer>,
+    format: String,
+    format_expanded: String,
+}
+
+impl GroupModule {
+    pub fn new(config: &GroupConfig, tx: UnboundedSender<()>) -> Self {
+        Self {
+            tx,
+            drawer: Arc::new(Drawer {
+                separator: config.separator.clone(),
+                expanded: AtomicBool::new(config.expanded),
+            }),
+            format: config.format.clone(),
+            format_expanded: config.format_expanded.clone().unwrap_or_else(|| config.format.clone()),
+        }
+    }
+
+    pub fn drawer(&self) -> Arc<Drawer> {
+        Arc::clone(&self.drawer)
+    }
+}
+
+#[async_trait]
+impl Module for GroupModule {
+    async fn run(&self) {}
+
+    async fn update(&self) {
+        let _ = self.tx.send(());
+    }
+
+    async fn get_value(&self) -> ModuleOutput {
+        let value = if self.drawer.expanded() { &self.format_expanded } else { &self.format };
+        ModuleOutput {
+            value: value.clone(),
+            ..Default::default()
+        }
+    }
+
+    fn toggle(&self) -> bool {
+        self.drawer.set_expanded(!self.drawer.expanded());
+        let _ = self.tx.send(());
+        true
+    }
+
+    fn buttons(&self) -> Vec<u8> {
+        vec![1]
+    }
+
+    async fn click(&self, button: u8) {
+        if button == 1 {
+            self.toggle();
+        }
+    }
+}
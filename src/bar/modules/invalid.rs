@@ -1,17 +1,15 @@
 use async_trait::async_trait;
-use std::sync::mpsc::Sender;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
+/// Placeholder for a module that could not be built, showing why
 #[derive(Debug)]
 pub struct InvalidModule {
-    value: String,
+    error: ModuleError,
 }
 
 impl InvalidModule {
-    pub fn new(name: &str, _tx: Sender<()>) -> Self {
-        Self {
-            value: format!("error: invalid module name: {}", name),
-        }
+    pub fn new(error: ModuleError) -> Self {
+        Self { error }
     }
 }
 
@@ -19,11 +17,9 @@ impl InvalidModule {
 impl Module for InvalidModule {
     async fn run(&self) {}
 
+    async fn update(&self) {}
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: None,
-            icon_color: None,
-            value: self.value.clone(),
-        }
+        ModuleOutput::from_result(None, None, Err(self.error.clone()))
     }
 }
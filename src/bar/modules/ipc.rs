@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Notify;
+use tokio::time::{Duration, Instant, sleep_until};
+use crate::config::IpcConfig;
+use crate::{Module, ModuleOutput};
+
+/// A value pushed with `barrs set`
+#[derive(Debug, Clone)]
+struct IpcValue {
+    text: String,
+    color: Option<String>,
+    expires: Option<Instant>,
+}
+
+/// Values of all ipc modules, keyed by module name
+///
+/// Kept outside of the modules so values survive a config reload.
+static VALUES: LazyLock<Mutex<HashMap<String, IpcValue>>> = LazyLock::new(Default::default);
+
+fn current_value(name: &str) -> Option<IpcValue> {
+    let mut values = VALUES.lock().unwrap();
+    match values.get(name) {
+        Some(value) if value.expires.is_some_and(|expires| expires <= Instant::now()) => {
+            values.remove(name);
+            None
+        }
+        value => value.cloned(),
+    }
+}
+
+/// Display text pushed from outside with `barrs set`
+#[derive(Debug)]
+pub struct IpcModule {
+    tx: UnboundedSender<()>,
+    name: String,
+    changed: Notify,
+    icon: Option<String>,
+    icon_color: Option<String>,
+    default: String,
+    color: Option<String>,
+    expire: Option<u64>,
+}
+
+impl IpcModule {
+    pub fn new(name: &str, config: &IpcConfig, tx: UnboundedSender<()>) -> Self {
+        Self {
+            tx,
+            name: name.to_string(),
+            changed: Notify::new(),
+            icon: config.icon.clone(),
+            icon_color: config.icon_color.clone(),
+            default: config.default.clone(),
+            color: config.color.clone(),
+            expire: config.expire,
+        }
+    }
+}
+
+#[async_trait]
+impl Module for IpcModule {
+    async fn run(&self) {
+        loop {
+            let expires = current_value(&self.name).and_then(|value| value.expires);
+            match expires {
+                Some(expires) => tokio::select! {
+                    _ = sleep_until(expires) => self.update().await,
+                    _ = self.changed.notified() => {}
+                },
+                None => self.changed.notified().await,
+            }
+        }
+    }
+
+    fn set_value(&self, text: String, color: Option<String>, expire: Option<u64>) -> bool {
+        let value = IpcValue {
+            text,
+            color,
+            expires: expire
+                .or(self.expire)
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+        };
+        VALUES.lock().unwrap().insert(self.name.clone(), value);
+        true
+    }
+
+    async fn update(&self) {
+        self.changed.notify_one();
+        let _ = self.tx.send(());
+    }
+
+    async fn get_value(&self) -> ModuleOutput {
+        let (value, color) = match current_value(&self.name) {
+            Some(value) => (value.text, value.color.or_else(|| self.color.clone())),
+            None => (self.default.clone(), self.color.clone()),
+        };
+
+        ModuleOutput {
+            icon: self.icon.clone(),
+            icon_color: self.icon_color.clone(),
+            value,
+            color,
+            ..Default::default()
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use std::fs;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use crate::config::KernelConfig;
@@ -9,7 +9,7 @@ use crate::{Module, ModuleOutput};
 /// Display date using a configured format
 #[derive(Debug)]
 pub struct KernelModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
     kernel_info: Mutex<String>,
     icon: Option<String>,
@@ -18,7 +18,7 @@ pub struct KernelModule {
 }
 
 impl KernelModule {
-    pub fn new(config: &KernelConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &KernelConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         Self {
             tx,
@@ -33,19 +33,28 @@ impl KernelModule {
 
 #[async_trait]
 impl Module for KernelModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.kernel_info.lock().await = kernel_info_from_string(&self.format);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.kernel_info.lock().await = kernel_info_from_string(&self.format);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
         ModuleOutput {
             icon: self.icon.clone(),
             icon_color: self.icon_color.clone(),
             value: self.kernel_info.lock().await.clone(),
+            ..Default::default()
         }
     }
 }
@@ -1,24 +1,24 @@
 use async_trait::async_trait;
 use std::fs;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use crate::config::LoadavgConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display average CPU load using a configured format
 #[derive(Debug)]
 pub struct LoadavgModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_loadavg: Mutex<String>,
+    current_loadavg: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format: String,
 }
 
 impl LoadavgModule {
-    pub fn new(config: &LoadavgConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &LoadavgConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         Self {
             tx,
@@ -33,32 +33,40 @@ impl LoadavgModule {
 
 #[async_trait]
 impl Module for LoadavgModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.current_loadavg.lock().await = loadavg_from_string(&self.format);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_loadavg.lock().await = loadavg_from_string(&self.format);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_loadavg.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_loadavg.lock().await.clone(),
+        )
     }
 }
 
-fn loadavg_from_string(format: &str) -> String {
+fn loadavg_from_string(format: &str) -> Result<String, ModuleError> {
     let contents = match fs::read_to_string("/proc/loadavg") {
         Ok(s) => s,
-        Err(_) => return "load: n/a".into(),
+        Err(e) => return Err(ModuleError::Unavailable(format!("could not read /proc/loadavg: {e}"))),
     };
 
     let parts: Vec<&str> = contents.split_whitespace().collect();
     if parts.len() < 4 {
-        return "load: n/a".into();
+        return Err(ModuleError::Parse("unexpected /proc/loadavg format".to_string()));
     }
 
     let one = parts[0].parse::<f64>().unwrap_or(0.0);
@@ -77,5 +85,5 @@ fn loadavg_from_string(format: &str) -> String {
     output = output.replace("{15m}", &format!("{:.2}", fifteen));
     output = output.replace("{total}", &format!("{}", total_processes));
 
-    output
+    Ok(output)
 }
@@ -1,17 +1,17 @@
 use async_trait::async_trait;
 use std::fs;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use crate::config::MemoryConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display sytem memory usage using a configured format
 #[derive(Debug)]
 pub struct MemoryModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_usage: Mutex<String>,
+    current_usage: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format: String,
@@ -19,7 +19,7 @@ pub struct MemoryModule {
 
 /// Display system memory usage using a configured format
 impl MemoryModule {
-    pub fn new(config: &MemoryConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &MemoryConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         Self {
             tx,
@@ -34,27 +34,35 @@ impl MemoryModule {
 
 #[async_trait]
 impl Module for MemoryModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.current_usage.lock().await = usage_from_string(&self.format);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_usage.lock().await = usage_from_string(&self.format);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_usage.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_usage.lock().await.clone(),
+        )
     }
 }
 
-fn usage_from_string(format: &str) -> String {
+fn usage_from_string(format: &str) -> Result<String, ModuleError> {
     let content = match fs::read_to_string("/proc/meminfo") {
         Ok(c) => c,
-        Err(_) => return "mem: n/a".into(),
+        Err(e) => return Err(ModuleError::Unavailable(format!("could not read /proc/meminfo: {e}"))),
     };
 
     let mut total_kb = 0u64;
@@ -81,11 +89,11 @@ fn usage_from_string(format: &str) -> String {
     let gb = used_kb as f64 / 1_000_000.0;
     let gib = used_kb as f64 / 1_048_576.0;
 
-    format
+    Ok(format
         .replace("{%}", &format!("{:.0}", percent))
         .replace("{mb}", &format!("{:.0}", mb))
         .replace("{mib}", &format!("{:.0}", mib))
         .replace("{gb}", &format!("{:.2}", gb))
-        .replace("{gib}", &format!("{:.2}", gib))
+        .replace("{gib}", &format!("{:.2}", gib)))
 }
 
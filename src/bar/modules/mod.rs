@@ -4,6 +4,11 @@ use battery::BatteryModule;
 mod brightnessctl;
 use brightnessctl::BrightnessctlModule;
 
+mod child;
+
+mod command;
+use command::CommandModule;
+
 mod cpu;
 use cpu::CpuModule;
 
@@ -13,12 +18,24 @@ use cputemp::CputempModule;
 mod date;
 use date::DateModule;
 
+mod external;
+use external::ExternalModule;
+
 mod filesystem;
 use filesystem::FilesystemModule;
 
+mod formats;
+use formats::Formats;
+
+mod group;
+use group::{Drawer, GroupModule};
+
 mod invalid;
 use invalid::InvalidModule;
 
+mod ipc;
+use ipc::IpcModule;
+
 mod kernel;
 use kernel::KernelModule;
 
@@ -31,6 +48,12 @@ use memory::MemoryModule;
 mod network;
 use network::NetworkModule;
 
+mod stream;
+use stream::StreamModule;
+
+mod toggle;
+use toggle::ToggleModule;
+
 mod uptime;
 use uptime::UptimeModule;
 
@@ -47,4 +70,4 @@ mod xworkspaces;
 use xworkspaces::XworkspacesModule;
 
 mod factory;
-pub use factory::build_modules;
+pub use factory::{Built, ModuleEntry, Registry, build_modules};
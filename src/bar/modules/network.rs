@@ -1,29 +1,32 @@
 use async_trait::async_trait;
 use if_addrs::get_if_addrs;
 use std::fs;
+use std::path::Path;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
-use tokio::time::{Duration, sleep};
+use tokio::time::{Duration, Instant, sleep};
 use crate::config::NetworkConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display information about a given network interface using a configured format
 #[derive(Debug)]
 pub struct NetworkModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_net: Mutex<String>,
+    /// Formatted value and operstate of the interface
+    current_net: Mutex<Result<(String, String), ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     interface: String,
     format: String,
     prev_rx: AtomicU64,
     prev_tx: AtomicU64,
+    prev_sample: Mutex<Instant>,
 }
 
 impl NetworkModule {
-    pub fn new(config: &NetworkConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &NetworkConfig, tx: UnboundedSender<()>) -> Self {
         let interval = config.interval;
         let interface = config.interface.clone();
         let format = config.format.clone();
@@ -32,7 +35,7 @@ impl NetworkModule {
             &format,
             0,
             0,
-            interval,
+            interval as f64,
         );
 
         Self {
@@ -45,35 +48,58 @@ impl NetworkModule {
             format,
             prev_rx: AtomicU64::new(prev_rx),
             prev_tx: AtomicU64::new(prev_tx),
+            prev_sample: Mutex::new(Instant::now()),
         }
     }
 }
 
 #[async_trait]
 impl Module for NetworkModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            let (current_net, prev_rx, prev_tx) = network_info_from_string(
-                &self.interface,
-                &self.format,
-                self.prev_rx.load(Ordering::SeqCst),
-                self.prev_tx.load(Ordering::SeqCst),
-                self.interval,
-            );
-            *self.current_net.lock().await = current_net;
-            self.prev_rx.store(prev_rx, Ordering::SeqCst);
-            self.prev_tx.store(prev_tx, Ordering::SeqCst);
-
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        // updates can be requested at any time, so measure the time
+        // since the previous sample instead of assuming the interval
+        let elapsed = {
+            let mut prev_sample = self.prev_sample.lock().await;
+            let elapsed = prev_sample.elapsed();
+            *prev_sample = Instant::now();
+            elapsed
+        };
+
+        let (current_net, prev_rx, prev_tx) = network_info_from_string(
+            &self.interface,
+            &self.format,
+            self.prev_rx.load(Ordering::SeqCst),
+            self.prev_tx.load(Ordering::SeqCst),
+            elapsed.as_secs_f64(),
+        );
+        *self.current_net.lock().await = current_net;
+        self.prev_rx.store(prev_rx, Ordering::SeqCst);
+        self.prev_tx.store(prev_tx, Ordering::SeqCst);
+
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
+        let current_net = self.current_net.lock().await.clone();
+        let state = current_net.as_ref().ok().map(|(_, state)| state.clone());
         ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_net.lock().await.clone(),
+            state,
+            ..ModuleOutput::from_result(
+                self.icon.clone(),
+                self.icon_color.clone(),
+                current_net.map(|(value, _)| value),
+            )
         }
     }
 }
@@ -129,13 +155,22 @@ pub fn network_info_from_string(
     format: &str,
     prev_rx: u64,
     prev_tx: u64,
-    delta_secs: u64,
-) -> (String, u64, u64) {
+    delta_secs: f64,
+) -> (Result<(String, String), ModuleError>, u64, u64) {
+    if !Path::new(&format!("/sys/class/net/{iface}")).exists() {
+        return (
+            Err(ModuleError::Unavailable(format!("network interface not found: {iface}"))),
+            prev_rx,
+            prev_tx,
+        );
+    }
+
     let state = read_state(iface);
     let (rx_bytes, tx_bytes) = read_bytes(iface);
 
-    let rx_speed_bps = rx_bytes.saturating_sub(prev_rx) / delta_secs;
-    let tx_speed_bps = tx_bytes.saturating_sub(prev_tx) / delta_secs;
+    let delta_secs = delta_secs.max(0.001);
+    let rx_speed_bps = (rx_bytes.saturating_sub(prev_rx) as f64 / delta_secs) as u64;
+    let tx_speed_bps = (tx_bytes.saturating_sub(prev_tx) as f64 / delta_secs) as u64;
 
     let (rx_bit, rx_kbit, rx_mbit, rx_gbit) = bytes_to_units(rx_speed_bps);
     let (tx_bit, tx_kbit, tx_mbit, tx_gbit) = bytes_to_units(tx_speed_bps);
@@ -153,5 +188,5 @@ pub fn network_info_from_string(
         .replace("{up_speed_mbit}", &format!("{:.2}", tx_mbit))
         .replace("{up_speed_gbit}", &format!("{:.2}", tx_gbit));
 
-    (formatted, rx_bytes, tx_bytes)
+    (Ok((formatted, state)), rx_bytes, tx_bytes)
 }
@@ -0,0 +1,154 @@
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use crate::bar::placeholders;
+use crate::config::StreamConfig;
+use crate::{Module, ModuleError, ModuleOutput};
+use super::child::{Backoff, spawn_shell};
+use super::Formats;
+
+/// Display the latest line printed by a long-running command
+#[derive(Debug)]
+pub struct StreamModule {
+    tx: UnboundedSender<()>,
+    /// The latest line in each format
+    current_line: Mutex<Formats<Result<String, ModuleError>>>,
+    exec: String,
+    shell: String,
+    icon: Option<String>,
+    icon_color: Option<String>,
+    formats: Formats<String>,
+    showing_alt: AtomicBool,
+    color: Option<String>,
+    json: bool,
+}
+
+impl StreamModule {
+    pub fn new(config: &StreamConfig, formats: Formats<String>, tx: UnboundedSender<()>) -> Self {
+        Self {
+            tx,
+            current_line: Mutex::new(formats.map(|_| Ok(String::new()))),
+            exec: config.exec.clone(),
+            shell: config.shell.clone(),
+            icon: config.icon.clone(),
+            icon_color: config.icon_color.clone(),
+            formats,
+            showing_alt: AtomicBool::new(false),
+            color: config.color.clone(),
+            json: config.json,
+        }
+    }
+
+    async fn output_for(&self, short: bool) -> ModuleOutput {
+        let mut output = ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_line.lock().await.shown(self.showing_alt.load(Ordering::SeqCst), short).clone(),
+        );
+        output.color = self.color.clone();
+        output
+    }
+
+    async fn set_line(&self, line: Formats<Result<String, ModuleError>>) {
+        *self.current_line.lock().await = line;
+        let _ = self.tx.send(());
+    }
+
+    /// Run the child until it exits, returning why it stopped
+    async fn stream(&self) -> ModuleError {
+        let mut child = match spawn_shell(&self.shell, &self.exec, Stdio::null()) {
+            Ok(child) => child,
+            Err(e) => return ModuleError::Command(format!("failed to execute {}: {e}", self.shell)),
+        };
+
+        if let Some(stdout) = child.stdout.take() {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                self.set_line(self.formats.map(|format| format_line(format, &line, self.json))).await;
+            }
+        }
+
+        match child.wait().await {
+            Ok(status) => ModuleError::Command(format!("{} exited ({status})", self.exec)),
+            Err(e) => ModuleError::Command(format!("{} failed: {e}", self.exec)),
+        }
+    }
+}
+
+#[async_trait]
+impl Module for StreamModule {
+    async fn run(&self) {
+        let mut backoff = Backoff::new();
+        loop {
+            backoff.start();
+            let error = self.stream().await;
+            let delay = backoff.next_delay();
+            let error = ModuleError::Command(format!("{error}, restarting in {}s", delay.as_secs()));
+            self.set_line(self.formats.map(|_| Err(error.clone()))).await;
+            sleep(delay).await;
+        }
+    }
+
+    async fn update(&self) {
+        let _ = self.tx.send(());
+    }
+
+    async fn get_value(&self) -> ModuleOutput {
+        self.output_for(false).await
+    }
+
+    async fn get_short_value(&self) -> Option<ModuleOutput> {
+        self.formats.short.as_ref()?;
+        Some(self.output_for(true).await)
+    }
+
+    fn switches_formats(&self) -> bool {
+        true
+    }
+
+    fn toggle(&self) -> bool {
+        if self.formats.alt.is_none() {
+            return false;
+        }
+        self.showing_alt.fetch_xor(true, Ordering::SeqCst);
+        let _ = self.tx.send(());
+        true
+    }
+
+    fn buttons(&self) -> Vec<u8> {
+        if self.formats.alt.is_some() { vec![1] } else { Vec::new() }
+    }
+
+    /// Toggle on a left click
+    async fn click(&self, button: u8) {
+        if button == 1 {
+            self.toggle();
+        }
+    }
+}
+
+fn format_line(format: &str, line: &str, json: bool) -> Result<String, ModuleError> {
+    let fields: Map<String, Value> = if json {
+        serde_json::from_str(line).map_err(|e| ModuleError::Parse(format!("invalid JSON line: {e}")))?
+    } else {
+        Map::new()
+    };
+    let fields: HashMap<String, String> = fields
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key, s),
+            value => (key, value.to_string()),
+        })
+        .collect();
+
+    Ok(placeholders::fill(format, |name| match name {
+        "line" => Some(line),
+        name => fields.get(name).map(String::as_str),
+    }))
+}
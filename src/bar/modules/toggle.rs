@@ -0,0 +1,95 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::mpsc::UnboundedSender;
+use crate::bar::Monitor;
+use crate::{Module, ModuleOutput};
+
+/// A module built twice, with its `format*` keys and with its
+/// `format_alt*` keys, showing one or the other
+///
+/// Both versions keep running, so either can be shown right away.
+/// Modules running commands switch formats themselves instead, see
+/// [`Module::switches_formats`].
+pub struct ToggleModule {
+    tx: UnboundedSender<()>,
+    main: Arc<dyn Module + Send + Sync>,
+    alt: Arc<dyn Module + Send + Sync>,
+    showing_alt: AtomicBool,
+}
+
+impl ToggleModule {
+    pub fn new(
+        main: Arc<dyn Module + Send + Sync>,
+        alt: Arc<dyn Module + Send + Sync>,
+        tx: UnboundedSender<()>,
+    ) -> Self {
+        Self {
+            tx,
+            main,
+            alt,
+            showing_alt: AtomicBool::new(false),
+        }
+    }
+
+    fn active(&self) -> &Arc<dyn Module + Send + Sync> {
+        if self.showing_alt.load(Ordering::SeqCst) { &self.alt } else { &self.main }
+    }
+}
+
+#[async_trait]
+impl Module for ToggleModule {
+    async fn run(&self) {
+        tokio::join!(self.main.run(), self.alt.run());
+    }
+
+    async fn update(&self) {
+        tokio::join!(self.main.update(), self.alt.update());
+    }
+
+    async fn get_value(&self) -> ModuleOutput {
+        self.active().get_value().await
+    }
+
+    async fn get_value_on(&self, monitor: &Monitor) -> ModuleOutput {
+        self.active().get_value_on(monitor).await
+    }
+
+    fn signal_id(&self) -> Option<u8> {
+        self.main.signal_id()
+    }
+
+    fn interval(&self) -> Option<u64> {
+        self.active().interval()
+    }
+
+    /// Set both versions, as either may be shown next
+    fn set_value(&self, text: String, color: Option<String>, expire: Option<u64>) -> bool {
+        self.alt.set_value(text.clone(), color.clone(), expire);
+        self.main.set_value(text, color, expire)
+    }
+
+    fn toggle(&self) -> bool {
+        self.showing_alt.fetch_xor(true, Ordering::SeqCst);
+        let _ = self.tx.send(());
+        true
+    }
+
+    fn buttons(&self) -> Vec<u8> {
+        let mut buttons = self.active().buttons();
+        if !buttons.contains(&1) {
+            buttons.insert(0, 1);
+        }
+        buttons
+    }
+
+    /// Toggle on a left click and leave other buttons to the module
+    async fn click(&self, button: u8) {
+        match button {
+            1 => {
+                self.toggle();
+            }
+            button => self.active().click(button).await,
+        }
+    }
+}
@@ -1,24 +1,24 @@
 use async_trait::async_trait;
 use std::fs;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use tokio::time::{Duration, sleep};
 use crate::config::UptimeConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display uptime using a configured format
 #[derive(Debug)]
 pub struct UptimeModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     interval: u64,
-    current_uptime: Mutex<String>,
+    current_uptime: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format: String,
 }
 
 impl UptimeModule {
-    pub fn new(config: &UptimeConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &UptimeConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         Self {
             tx,
@@ -33,26 +33,34 @@ impl UptimeModule {
 
 #[async_trait]
 impl Module for UptimeModule {
+    fn interval(&self) -> Option<u64> {
+        Some(self.interval)
+    }
+
     async fn run(&self) {
         loop {
-            *self.current_uptime.lock().await = uptime_from_string(&self.format);
-            let _ = self.tx.send(());
+            self.update().await;
             sleep(Duration::from_secs(self.interval)).await;
         }
     }
 
+    async fn update(&self) {
+        *self.current_uptime.lock().await = uptime_from_string(&self.format);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_uptime.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_uptime.lock().await.clone(),
+        )
     }
 }
 
-fn uptime_from_string(format: &str) -> String {
+fn uptime_from_string(format: &str) -> Result<String, ModuleError> {
     let uptime_contents = fs::read_to_string("/proc/uptime")
-        .unwrap_or_else(|_| "0.0 0.0".to_string());
+        .map_err(|e| ModuleError::Unavailable(format!("could not read /proc/uptime: {e}")))?;
 
     let uptime_seconds: u64 = uptime_contents
         .split_whitespace()
@@ -81,7 +89,7 @@ fn uptime_from_string(format: &str) -> String {
     let hours = hours.to_string();
     let days = days.to_string();
 
-    format
+    Ok(format
         .replace("{total_days}", &total_days)
         .replace("{total_hours}", &total_hours)
         .replace("{total_minutes}", &total_minutes)
@@ -89,5 +97,5 @@ fn uptime_from_string(format: &str) -> String {
         .replace("{days}", &days)
         .replace("{hours}", &hours)
         .replace("{minutes}", &minutes)
-        .replace("{seconds}", &seconds)
+        .replace("{seconds}", &seconds))
 }
@@ -1,16 +1,16 @@
 use async_trait::async_trait;
 use std::process::Command;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use crate::config::WpctlConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display wpctl info using a configured format
 #[derive(Debug)]
 pub struct WpctlModule {
-    tx: Sender<()>,
+    tx: UnboundedSender<()>,
     signal_id: Option<u8>,
-    current_audio: Mutex<String>,
+    current_audio: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format: String,
@@ -18,7 +18,7 @@ pub struct WpctlModule {
 }
 
 impl WpctlModule {
-    pub fn new(config: &WpctlConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &WpctlConfig, tx: UnboundedSender<()>) -> Self {
         let format = config.format.clone();
         let format_muted = config.format_muted.clone();
         Self {
@@ -40,34 +40,37 @@ impl Module for WpctlModule {
     }
 
     async fn run(&self) {
+        self.update().await;
+    }
+
+    async fn update(&self) {
         *self.current_audio.lock().await = audio_from_string(&self.format, &self.format_muted);
         let _ = self.tx.send(());
     }
 
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_audio.lock().await.clone(),
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_audio.lock().await.clone(),
+        )
     }
 }
 
-fn audio_from_string(format: &str, format_muted: &str) -> String {
+fn audio_from_string(format: &str, format_muted: &str) -> Result<String, ModuleError> {
     let output = Command::new("wpctl")
         .args(["get-volume", "@DEFAULT_AUDIO_SINK@"])
         .output();
 
     let output = match output {
         Ok(out) => out,
-        Err(_) => return "error: failed to execute wpctl".to_string(),
+        Err(e) => return Err(ModuleError::Command(format!("failed to execute wpctl: {e}"))),
     };
 
     if !output.status.success() {
-        return format!(
-            "error: {}",
-            str::from_utf8(&output.stderr).unwrap_or("unknown error"),
-        );
+        return Err(ModuleError::Command(
+            str::from_utf8(&output.stderr).unwrap_or("unknown error").trim().to_string(),
+        ));
     }
 
     let stdout = str::from_utf8(&output.stdout).unwrap_or("");
@@ -90,8 +93,8 @@ fn audio_from_string(format: &str, format_muted: &str) -> String {
     let volume_percent = volume.unwrap_or(0.0) * 100.0;
 
     if is_muted {
-        format_muted.replace("{volume}", &format!("{:.0}", volume_percent))
+        Ok(format_muted.replace("{volume}", &format!("{:.0}", volume_percent)))
     } else {
-        format.replace("{volume}", &format!("{:.0}", volume_percent))
+        Ok(format.replace("{volume}", &format!("{:.0}", volume_percent)))
     }
 }
@@ -1,23 +1,24 @@
 use async_trait::async_trait;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{self, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask};
+use x11rb::protocol::xproto::{self, AtomEnum, EventMask};
 use x11rb::rust_connection::RustConnection;
+use crate::bar::xevents::XEvents;
 use crate::config::XkeyboardConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display current keyboard layout on X11
 #[derive(Debug)]
 pub struct XkeyboardModule {
-    tx: Sender<()>,
-    current_layout: Mutex<String>,
+    tx: UnboundedSender<()>,
+    current_layout: Mutex<Result<String, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
 }
 
 impl XkeyboardModule {
-    pub fn new(config: &XkeyboardConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &XkeyboardConfig, tx: UnboundedSender<()>) -> Self {
         Self {
             tx,
             current_layout: Mutex::new(get_current_keyboard_layout()),
@@ -25,52 +26,51 @@ impl XkeyboardModule {
             icon_color: config.icon_color.clone(),
         }
     }
+
+    async fn watch(&self) -> anyhow::Result<()> {
+        let events = XEvents::watch_root(EventMask::PROPERTY_CHANGE | EventMask::KEYMAP_STATE)?;
+        loop {
+            events.wait().await?;
+            self.update().await;
+        }
+    }
 }
 
 #[async_trait]
 impl Module for XkeyboardModule {
     async fn run(&self) {
-        let (conn, screen_num) = RustConnection::connect(None).unwrap();
-        let screen = &conn.setup().roots[screen_num];
-        let root = screen.root;
-        conn.change_window_attributes(
-            root,
-            &ChangeWindowAttributesAux::new()
-            .event_mask(EventMask::PROPERTY_CHANGE | EventMask::KEYMAP_STATE),
-        ).unwrap();
-        conn.flush().unwrap();
-
-        loop {
-            conn.wait_for_event().unwrap();
-            *self.current_layout.lock().await = get_current_keyboard_layout();
-            let _ = self.tx.send(());
+        if let Err(e) = self.watch().await {
+            eprintln!("warning: xkeyboard stopped watching X events: {e}");
         }
     }
 
+    async fn update(&self) {
+        *self.current_layout.lock().await = get_current_keyboard_layout();
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_layout.lock().await.clone()
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_layout.lock().await.clone(),
+        )
     }
 }
 
-fn get_current_keyboard_layout() -> String {
+fn get_current_keyboard_layout() -> Result<String, ModuleError> {
     // Connect to the X server
-    let (conn, screen_num) = match RustConnection::connect(None) {
-        Ok(r) => r,
-        Err(e) => return format!("error: {e}"),
-    };
+    let (conn, screen_num) = RustConnection::connect(None)
+        .map_err(|e| ModuleError::X11(e.to_string()))?;
     let root = conn.setup().roots[screen_num].root;
 
     // Intern the atom for "_XKB_RULES_NAMES"
     let atom = match xproto::intern_atom(&conn, false, b"_XKB_RULES_NAMES") {
         Ok(cookie) => match cookie.reply() {
             Ok(atom_reply) => atom_reply.atom,
-            Err(_) => return "unknown".to_string(),
+            Err(_) => return Ok("unknown".to_string()),
         },
-        Err(_) => return "unknown".to_string(),
+        Err(_) => return Ok("unknown".to_string()),
     };
 
     // Get the property data from the root window
@@ -85,9 +85,9 @@ fn get_current_keyboard_layout() -> String {
     ) {
         Ok(cookie) => match cookie.reply() {
             Ok(prop_reply) => prop_reply,
-            Err(_) => return "unknown".to_string(),
+            Err(_) => return Ok("unknown".to_string()),
         },
-        Err(_) => return "unknown".to_string(),
+        Err(_) => return Ok("unknown".to_string()),
     };
 
     // Convert to a Rust String
@@ -96,8 +96,8 @@ fn get_current_keyboard_layout() -> String {
     // The property value is NUL-separated: rules\0model\0layout\0variant\0options\0
     let parts: Vec<&str> = text.split('\0').collect();
     if parts.len() > 2 && !parts[2].is_empty() {
-        parts[2].to_string()
+        Ok(parts[2].to_string())
     } else {
-        "unknown".to_string()
+        Ok("unknown".to_string())
     }
 }
@@ -1,71 +1,105 @@
 use async_trait::async_trait;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask};
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, EventMask, Window};
 use x11rb::rust_connection::RustConnection;
+use crate::bar::Monitor;
 use crate::config::XwindowConfig;
-use crate::{Module, ModuleOutput};
+use crate::bar::width::cut;
+use crate::bar::xevents::XEvents;
+use crate::{Module, ModuleError, ModuleOutput};
+
+/// Longest title read, in 32-bit units, which is then shortened on
+/// grapheme cluster boundaries. Longer titles lose their incomplete
+/// last character, see [`utf8_title`].
+const TITLE_LENGTH: u32 = 1024;
 
 /// Display current window name on X11
 #[derive(Debug)]
 pub struct XwindowModule {
-    tx: Sender<()>,
-    current_window: Mutex<String>,
+    tx: UnboundedSender<()>,
+    current_window: Mutex<Result<ActiveWindow, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
-    max_length: u32,
+    max_length: Option<usize>,
     user_empty_string: String,
 }
 
+/// The focused window
+#[derive(Debug, Clone)]
+struct ActiveWindow {
+    title: String,
+    /// Center of the window on the screen, `None` without a window
+    center: Option<(i32, i32)>,
+}
+
 impl XwindowModule {
-    pub fn new(config: &XwindowConfig, tx: Sender<()>) -> Self {
+    pub fn new(config: &XwindowConfig, tx: UnboundedSender<()>) -> Self {
         let max_length = config.max_length;
         let user_empty_string = config.empty_name.clone();
         Self {
             tx,
-            current_window: Mutex::new(get_active_window_title(max_length, &user_empty_string)),
+            current_window: Mutex::new(get_active_window(max_length, &user_empty_string)),
             icon: config.icon.clone(),
             icon_color: config.icon_color.clone(),
             max_length,
             user_empty_string,
         }
     }
+
+    async fn watch(&self) -> anyhow::Result<()> {
+        let events = XEvents::watch_root(EventMask::PROPERTY_CHANGE)?;
+        loop {
+            events.wait().await?;
+            self.update().await;
+        }
+    }
 }
 
 #[async_trait]
 impl Module for XwindowModule {
     async fn run(&self) {
-        let (conn, screen_num) = RustConnection::connect(None).unwrap();
-        let screen = &conn.setup().roots[screen_num];
-        let root = screen.root;
-        conn.change_window_attributes(
-            root,
-            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
-        ).unwrap();
-        conn.flush().unwrap();
-
-        loop {
-            conn.wait_for_event().unwrap();
-            *self.current_window.lock().await = get_active_window_title(self.max_length, &self.user_empty_string);
-            let _ = self.tx.send(());
+        if let Err(e) = self.watch().await {
+            eprintln!("warning: xwindow stopped watching X events: {e}");
         }
     }
 
+    async fn update(&self) {
+        *self.current_window.lock().await = get_active_window(self.max_length, &self.user_empty_string);
+        let _ = self.tx.send(());
+    }
+
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_window.lock().await.clone()
-        }
+        ModuleOutput::from_result(
+            self.icon.clone(),
+            self.icon_color.clone(),
+            self.current_window.lock().await.clone().map(|window| window.title),
+        )
+    }
+
+    /// Show the title on the monitor the window is on, and `empty_name`
+    /// on the others
+    async fn get_value_on(&self, monitor: &Monitor) -> ModuleOutput {
+        let result = self.current_window.lock().await.clone().map(|window| match window.center {
+            Some((x, y)) if !monitor.contains(x, y) => self.user_empty_string.clone(),
+            _ => window.title,
+        });
+        ModuleOutput::from_result(self.icon.clone(), self.icon_color.clone(), result)
     }
 }
 
-fn get_active_window_title(max_length: u32, user_empty_string: &str) -> String {
-    let (conn, screen_num) = match RustConnection::connect(None) {
-        Ok(result) => result,
-        Err(e) => return format!("error: {e}"),
-    };
+fn get_active_window(max_length: Option<usize>, user_empty_string: &str) -> Result<ActiveWindow, ModuleError> {
+    let mut window = read_active_window(user_empty_string)?;
+    if let Some(max_length) = max_length {
+        window.title = cut(&window.title, max_length);
+    }
+    Ok(window)
+}
+
+fn read_active_window(user_empty_string: &str) -> Result<ActiveWindow, ModuleError> {
+    let (conn, screen_num) = RustConnection::connect(None)
+        .map_err(|e| ModuleError::X11(e.to_string()))?;
 
     let root = conn.setup().roots[screen_num].root;
 
@@ -75,7 +109,7 @@ fn get_active_window_title(max_length: u32, user_empty_string: &str) -> String {
         .reply()
     {
         Ok(r) => r.atom,
-        Err(_) => return "Unknown window".to_string(),
+        Err(_) => return Ok(ActiveWindow { title: "Unknown window".to_string(), center: None }),
     };
 
     let active_window_id = match conn
@@ -91,9 +125,26 @@ fn get_active_window_title(max_length: u32, user_empty_string: &str) -> String {
     };
 
     if active_window_id == 0 {
-        return user_empty_string.to_string();
+        return Ok(ActiveWindow { title: user_empty_string.to_string(), center: None });
     }
 
+    Ok(ActiveWindow {
+        title: read_window_title(&conn, active_window_id),
+        center: window_center(&conn, root, active_window_id),
+    })
+}
+
+/// Center of a window in root window coordinates
+fn window_center(conn: &RustConnection, root: Window, window: Window) -> Option<(i32, i32)> {
+    let geometry = conn.get_geometry(window).ok()?.reply().ok()?;
+    let position = conn.translate_coordinates(window, root, 0, 0).ok()?.reply().ok()?;
+    Some((
+        i32::from(position.dst_x) + i32::from(geometry.width) / 2,
+        i32::from(position.dst_y) + i32::from(geometry.height) / 2,
+    ))
+}
+
+fn read_window_title(conn: &RustConnection, active_window_id: Window) -> String {
     let net_wm_name = conn
         .intern_atom(false, b"_NET_WM_NAME")
         .unwrap()
@@ -125,28 +176,28 @@ fn get_active_window_title(max_length: u32, user_empty_string: &str) -> String {
     // 1. _NET_WM_NAME (UTF8_STRING)
     if let Some(atom) = net_wm_name
     && let Ok(reply) = conn
-        .get_property(false, active_window_id, atom, utf8_string, 0, max_length)
+        .get_property(false, active_window_id, atom, utf8_string, 0, TITLE_LENGTH)
         .unwrap()
         .reply()
     && !reply.value.is_empty() {
-        return String::from_utf8_lossy(&reply.value).to_string();
+        return utf8_title(&reply.value, reply.bytes_after);
     }
 
     // 2. WM_NAME (COMPOUND_TEXT)
     if let Some(atom) = wm_name
     && let Ok(reply) = conn
-        .get_property(false, active_window_id, atom, compound_text, 0, max_length)
+        .get_property(false, active_window_id, atom, compound_text, 0, TITLE_LENGTH)
         .unwrap()
         .reply()
     && !reply.value.is_empty() {
         // Best-effort decode; spec-correct decoding requires Xlib helpers
-        return String::from_utf8_lossy(&reply.value).to_string();
+        return utf8_title(&reply.value, reply.bytes_after);
     }
 
     // 3. WM_NAME (STRING, Latin-1)
     if let Some(atom) = wm_name
     && let Ok(reply) = conn
-        .get_property(false, active_window_id, atom, AtomEnum::STRING, 0, max_length)
+        .get_property(false, active_window_id, atom, AtomEnum::STRING, 0, TITLE_LENGTH)
         .unwrap()
         .reply()
     && !reply.value.is_empty() {
@@ -155,3 +206,45 @@ fn get_active_window_title(max_length: u32, user_empty_string: &str) -> String {
 
     "Unknown window".to_string()
 }
+
+/// Decode a title read as UTF-8, dropping the last character when the
+/// read stopped inside it, which happens when `bytes_after` is left
+fn utf8_title(value: &[u8], bytes_after: u32) -> String {
+    let mut end = value.len();
+    if bytes_after > 0 {
+        // the last character starts at most 3 bytes before the end
+        if let Some(back) = (1..=value.len().min(4)).find(|back| value[value.len() - back] & 0xC0 != 0x80) {
+            let length = match value[value.len() - back] {
+                0xC0..=0xDF => 2,
+                0xE0..=0xEF => 3,
+                0xF0..=0xF7 => 4,
+                _ => 1,
+            };
+            if length > back {
+                end -= back;
+            }
+        }
+    }
+    String::from_utf8_lossy(&value[..end]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn utf8_title_drops_cut_character() {
+        let title = "ab€".as_bytes();
+        assert_eq!(utf8_title(&title[..4], 4), "ab");
+        assert_eq!(utf8_title(&title[..3], 4), "ab");
+        assert_eq!(utf8_title(title, 4), "ab€");
+        assert_eq!(utf8_title("a😀".as_bytes(), 0), "a😀");
+        assert_eq!(utf8_title(&"a😀".as_bytes()[..4], 1), "a");
+    }
+
+    #[test]
+    fn utf8_title_keeps_complete_reads() {
+        // without bytes left, invalid bytes are shown as replacements
+        assert_eq!(utf8_title(&"ab€".as_bytes()[..4], 0), "ab\u{FFFD}");
+    }
+}
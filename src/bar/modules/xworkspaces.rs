@@ -1,91 +1,114 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use std::collections::HashSet;
-use std::sync::mpsc::Sender;
+use tokio::sync::mpsc::UnboundedSender;
 use tokio::sync::Mutex;
 use x11rb::connection::Connection;
-use x11rb::protocol::xproto::{AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask};
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, EventMask};
 use x11rb::rust_connection::RustConnection;
+use crate::bar::Monitor;
+use crate::bar::xevents::XEvents;
 use crate::config::XworkspacesConfig;
-use crate::{Module, ModuleOutput};
+use crate::{Module, ModuleError, ModuleOutput};
 
 /// Display X11 workspaces using a configured format
 #[derive(Debug)]
 pub struct XworkspacesModule {
-    tx: Sender<()>,
-    current_layout: Mutex<String>,
+    tx: UnboundedSender<()>,
+    workspaces: Mutex<Result<Vec<Workspace>, ModuleError>>,
     icon: Option<String>,
     icon_color: Option<String>,
     format_active: String,
     format_empty: String,
     format_occupied: String,
     format_urgent: String,
-    sepparator: String,
+    separator: String,
+    pin_workspaces: bool,
 }
 
 impl XworkspacesModule {
-    pub fn new(config: &XworkspacesConfig, tx: Sender<()>) -> Self {
-        let format_active = config.format_active.clone();
-        let format_empty = config.format_empty.clone();
-        let format_occupied = config.format_occupied.clone();
-        let format_urgent = config.format_urgent.clone();
-        let sepparator = config.sepparator.clone();
+    pub fn new(config: &XworkspacesConfig, tx: UnboundedSender<()>) -> Self {
         Self {
             tx,
-            current_layout: Mutex::new(format_workspaces(
-                &format_active,
-                &format_empty,
-                &format_occupied,
-                &format_urgent,
-                &sepparator,
-            )),
+            workspaces: Mutex::new(read_workspaces()),
             icon: config.icon.clone(),
             icon_color: config.icon_color.clone(),
-            format_active,
-            format_empty,
-            format_occupied,
-            format_urgent,
-            sepparator,
+            format_active: config.format_active.clone(),
+            format_empty: config.format_empty.clone(),
+            format_occupied: config.format_occupied.clone(),
+            format_urgent: config.format_urgent.clone(),
+            separator: config.separator.clone(),
+            pin_workspaces: config.pin_workspaces,
         }
     }
-}
-
-#[async_trait]
-impl Module for XworkspacesModule {
-    async fn run(&self) {
-        let (conn, screen_num) = RustConnection::connect(None).unwrap();
-        let screen = &conn.setup().roots[screen_num];
-        let root = screen.root;
-        conn.change_window_attributes(
-            root,
-            &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
-        ).unwrap();
-        conn.flush().unwrap();
 
+    async fn watch(&self) -> Result<()> {
+        let events = XEvents::watch_root(EventMask::PROPERTY_CHANGE)?;
         loop {
             // Not filtering to only update on active workspace change
             // causes multiple redraws, but is necessary for updates to
             // urgent/occupied fields without changing active workspace
-            conn.wait_for_event().unwrap();
+            events.wait().await?;
+            self.update().await;
+        }
+    }
+
+    /// Output of the workspaces `keep` lets through
+    async fn output(&self, keep: impl Fn(&Workspace) -> bool) -> ModuleOutput {
+        let result = match &*self.workspaces.lock().await {
+            Ok(workspaces) => Ok(self.format(workspaces.iter().filter(|workspace| keep(workspace)))),
+            Err(e) => Err(e.clone()),
+        };
+        ModuleOutput::from_result(self.icon.clone(), self.icon_color.clone(), result)
+    }
 
-            *self.current_layout.lock().await = format_workspaces(
-                &self.format_active,
-                &self.format_empty,
-                &self.format_occupied,
-                &self.format_urgent,
-                &self.sepparator,
-            );
+    fn format<'a>(&self, workspaces: impl Iterator<Item = &'a Workspace>) -> String {
+        let mut parts = Vec::new();
 
-            let _ = self.tx.send(());
+        for ws in workspaces {
+            let template = match ws.state {
+                WorkspaceState::Active => &self.format_active,
+                WorkspaceState::Empty => &self.format_empty,
+                WorkspaceState::Occupied => &self.format_occupied,
+                WorkspaceState::Urgent => &self.format_urgent,
+            };
+
+            let formatted = template
+                .replace("{index}", &ws.index.to_string())
+                .replace("{name}", &ws.name);
+
+            parts.push(formatted);
         }
+
+        parts.join(&self.separator)
+    }
+}
+
+#[async_trait]
+impl Module for XworkspacesModule {
+    async fn run(&self) {
+        if let Err(e) = self.watch().await {
+            eprintln!("warning: xworkspaces stopped watching X events: {e}");
+        }
+    }
+
+    async fn update(&self) {
+        *self.workspaces.lock().await = read_workspaces();
+
+        let _ = self.tx.send(());
     }
 
     async fn get_value(&self) -> ModuleOutput {
-        ModuleOutput {
-            icon: self.icon.clone(),
-            icon_color: self.icon_color.clone(),
-            value: self.current_layout.lock().await.clone()
+        self.output(|_| true).await
+    }
+
+    /// Show the workspaces whose viewport is on the monitor with
+    /// `pin_workspaces`, and those without a viewport everywhere
+    async fn get_value_on(&self, monitor: &Monitor) -> ModuleOutput {
+        if !self.pin_workspaces {
+            return self.get_value().await;
         }
+        self.output(|workspace| workspace.viewport.is_none_or(|(x, y)| monitor.contains(x, y))).await
     }
 }
 
@@ -95,7 +118,6 @@ enum WorkspaceState {
     Empty,
     Occupied,
     Urgent,
-    Error,
 }
 
 #[derive(Debug)]
@@ -103,17 +125,8 @@ struct Workspace {
     pub index: usize,
     pub name: String,
     pub state: WorkspaceState,
-}
-
-fn get_workspaces_result_wrapper() -> Vec<Workspace> {
-    match get_workspaces() {
-        Ok(result) => result,
-        Err(e) => vec!(Workspace{
-            index: 0,
-            name: format!("error: {e}"),
-            state: WorkspaceState::Error,
-        })
-    }
+    /// Top left corner of the workspace on the screen
+    pub viewport: Option<(i32, i32)>,
 }
 
 fn get_workspaces() -> Result<Vec<Workspace>> {
@@ -127,6 +140,8 @@ fn get_workspaces() -> Result<Vec<Workspace>> {
         conn.intern_atom(false, b"_NET_CURRENT_DESKTOP")?.reply()?.atom;
     let net_desktop_names =
         conn.intern_atom(false, b"_NET_DESKTOP_NAMES")?.reply()?.atom;
+    let net_desktop_viewport =
+        conn.intern_atom(false, b"_NET_DESKTOP_VIEWPORT")?.reply()?.atom;
     let net_client_list =
         conn.intern_atom(false, b"_NET_CLIENT_LIST")?.reply()?.atom;
     let net_wm_desktop =
@@ -169,6 +184,15 @@ fn get_workspaces() -> Result<Vec<Workspace>> {
 
     names.resize(num_desktops, String::new());
 
+    // x and y of each desktop, which window managers with a desktop
+    // per monitor point at the monitor showing it
+    let viewports: Vec<u32> = conn
+        .get_property(false, root, net_desktop_viewport, AtomEnum::CARDINAL, 0, u32::MAX)?
+        .reply()?
+        .value32()
+        .map(|values| values.collect())
+        .unwrap_or_default();
+
     let mut occupied = vec![false; num_desktops];
     let mut urgent = HashSet::new();
 
@@ -217,37 +241,13 @@ fn get_workspaces() -> Result<Vec<Workspace>> {
             index: i,
             name: names[i].clone(),
             state,
+            viewport: viewports.get(2 * i..2 * i + 2).map(|point| (point[0] as i32, point[1] as i32)),
         });
     }
 
     Ok(result)
 }
 
-fn format_workspaces(
-    format_active: &str,
-    format_empty: &str,
-    format_occupied: &str,
-    format_urgent: &str,
-    separator: &str,
-) -> String {
-    let workspaces = get_workspaces_result_wrapper();
-    let mut parts = Vec::with_capacity(workspaces.len());
-
-    for ws in workspaces {
-        let template = match ws.state {
-            WorkspaceState::Active => format_active,
-            WorkspaceState::Empty => format_empty,
-            WorkspaceState::Occupied => format_occupied,
-            WorkspaceState::Urgent => format_urgent,
-            WorkspaceState::Error => "",
-        };
-
-        let formatted = template
-            .replace("{index}", &ws.index.to_string())
-            .replace("{name}", &ws.name);
-
-        parts.push(formatted);
-    }
-
-    parts.join(separator)
+fn read_workspaces() -> Result<Vec<Workspace>, ModuleError> {
+    get_workspaces().map_err(|e| ModuleError::X11(e.to_string()))
 }
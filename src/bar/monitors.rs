@@ -0,0 +1,155 @@
+use anyhow::Result;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::randr::{self, ConnectionExt, NotifyMask};
+use x11rb::rust_connection::RustConnection;
+use super::XEvents;
+
+/// A RandR output lighting part of the X screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Monitor {
+    /// Name of the output, e.g. `HDMI-1`
+    pub name: String,
+    /// Position among the monitors ordered left to right, which is how
+    /// lemonbar numbers them in `%{Sn}`
+    pub index: usize,
+    pub x: i16,
+    pub y: i16,
+    pub width: u16,
+    pub height: u16,
+    pub primary: bool,
+}
+
+impl Monitor {
+    /// Whether a point of the screen is shown on this monitor
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let (left, top) = (i32::from(self.x), i32::from(self.y));
+        (left..left + i32::from(self.width)).contains(&x) && (top..top + i32::from(self.height)).contains(&y)
+    }
+
+    /// Whether the monitor is the one named, where `primary` names the
+    /// primary output
+    pub fn is(&self, name: &str) -> bool {
+        self.name == name || (name == "primary" && self.primary)
+    }
+
+    fn covers(&self, other: &Monitor) -> bool {
+        self.x <= other.x
+            && self.y <= other.y
+            && i32::from(self.x) + i32::from(self.width) >= i32::from(other.x) + i32::from(other.width)
+            && i32::from(self.y) + i32::from(self.height) >= i32::from(other.y) + i32::from(other.height)
+    }
+}
+
+/// The monitors of the X screen, for bars drawn once per monitor
+#[derive(Default)]
+pub struct Monitors {
+    monitors: Arc<RwLock<Vec<Monitor>>>,
+}
+
+impl Monitors {
+    /// List the monitors when a bar is drawn per monitor, without
+    /// connecting to X otherwise
+    pub fn new(enabled: bool) -> Self {
+        let monitors = Self::default();
+        if enabled {
+            match connect().and_then(|events| read_monitors(events.conn(), events.root())) {
+                Ok(list) => *monitors.monitors.write().unwrap() = list,
+                Err(e) => eprintln!("warning: could not list monitors: {e}"),
+            }
+        }
+        monitors
+    }
+
+    pub fn list(&self) -> Vec<Monitor> {
+        self.monitors.read().unwrap().clone()
+    }
+
+    /// List the monitors again whenever outputs are plugged in, turned
+    /// off or moved, and redraw the bar
+    pub fn watch(&self, tx: UnboundedSender<()>) -> Option<JoinHandle<()>> {
+        if self.monitors.read().unwrap().is_empty() {
+            return None;
+        }
+
+        let monitors = Arc::clone(&self.monitors);
+        Some(tokio::spawn(async move {
+            if let Err(e) = watch_monitors(&monitors, &tx).await {
+                eprintln!("warning: stopped watching monitors: {e}");
+            }
+        }))
+    }
+}
+
+fn connect() -> Result<XEvents> {
+    let events = XEvents::connect()?;
+    events.conn().randr_query_version(1, 2)?.reply()?;
+    Ok(events)
+}
+
+async fn watch_monitors(monitors: &RwLock<Vec<Monitor>>, tx: &UnboundedSender<()>) -> Result<()> {
+    let events = connect()?;
+    let (conn, root) = (events.conn(), events.root());
+    conn.randr_select_input(
+        root,
+        NotifyMask::SCREEN_CHANGE | NotifyMask::CRTC_CHANGE | NotifyMask::OUTPUT_CHANGE,
+    )?;
+    conn.flush()?;
+
+    loop {
+        let changed = events
+            .wait()
+            .await?
+            .into_iter()
+            .any(|event| matches!(event, Event::RandrScreenChangeNotify(_) | Event::RandrNotify(_)));
+        if changed {
+            let list = read_monitors(conn, root)?;
+            let mut current = monitors.write().unwrap();
+            if *current != list {
+                *current = list;
+                let _ = tx.send(());
+            }
+        }
+    }
+}
+
+/// The outputs showing part of the screen, ordered left to right
+fn read_monitors(conn: &RustConnection, root: u32) -> Result<Vec<Monitor>> {
+    let resources = conn.randr_get_screen_resources_current(root)?.reply()?;
+    let primary = conn.randr_get_output_primary(root)?.reply()?.output;
+
+    let mut monitors: Vec<Monitor> = Vec::new();
+    for &output in &resources.outputs {
+        let info = conn.randr_get_output_info(output, resources.config_timestamp)?.reply()?;
+        if info.connection != randr::Connection::CONNECTED || info.crtc == 0 {
+            continue;
+        }
+        let crtc = conn.randr_get_crtc_info(info.crtc, resources.config_timestamp)?.reply()?;
+        monitors.push(Monitor {
+            name: String::from_utf8_lossy(&info.name).into_owned(),
+            index: 0,
+            x: crtc.x,
+            y: crtc.y,
+            width: crtc.width,
+            height: crtc.height,
+            primary: output == primary,
+        });
+    }
+
+    // lemonbar leaves out monitors inside another one, e.g. mirrored
+    // outputs, so they are left out here too to keep the numbering
+    let all = monitors.clone();
+    monitors.retain(|monitor| {
+        !all.iter().any(|other| {
+            other != monitor && other.covers(monitor) && (!monitor.covers(other) || other.name < monitor.name)
+        })
+    });
+    monitors.sort_by_key(|monitor| (monitor.x, monitor.y));
+    for (index, monitor) in monitors.iter_mut().enumerate() {
+        monitor.index = index;
+    }
+    Ok(monitors)
+}
@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use std::ffi::CString;
+use std::io::{ErrorKind, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::net::unix::pipe;
+use tokio::process::{ChildStdin, Command};
+use crate::config::OutputConfig;
+
+/// A rendering of one bar, along with where it goes
+pub struct Frame {
+    pub output: OutputConfig,
+    pub text: String,
+}
+
+/// An open destination for the frames of a bar
+pub enum Output {
+    Stdout(std::io::Stdout),
+    Pipe(pipe::Sender),
+    Command(ChildStdin),
+}
+
+impl Output {
+    pub fn open(config: &OutputConfig) -> Result<Self> {
+        Ok(match config {
+            OutputConfig::Stdout => Output::Stdout(std::io::stdout()),
+            OutputConfig::Pipe(path) => {
+                if !path.exists() {
+                    make_fifo(path)?;
+                }
+                // opened for reading as well, so opening does not wait
+                // for a reader and writing does not fail without one
+                let sender = pipe::OpenOptions::new()
+                    .read_write(true)
+                    .open_sender(path)
+                    .with_context(|| format!("failed to open {}", path.display()))?;
+                Output::Pipe(sender)
+            }
+            OutputConfig::Command(command) => {
+                let mut child = Command::new("sh")
+                    .arg("-c")
+                    .arg(command)
+                    .stdin(Stdio::piped())
+                    .spawn()
+                    .with_context(|| format!("failed to run `{command}`"))?;
+                let stdin = child.stdin.take().context("failed to open the command's stdin")?;
+                Output::Command(stdin)
+            }
+        })
+    }
+
+    /// Write a frame followed by a newline
+    pub async fn write_frame(&mut self, frame: &str) -> std::io::Result<()> {
+        let line = format!("{frame}\n");
+        match self {
+            Output::Stdout(stdout) => stdout.write_all(line.as_bytes()),
+            // frames are dropped while the pipe is full, rather than
+            // holding every other bar back until someone reads it
+            Output::Pipe(sender) => match sender.try_write(line.as_bytes()) {
+                Ok(written) => sender.write_all(&line.as_bytes()[written..]).await,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(()),
+                Err(e) => Err(e),
+            },
+            Output::Command(stdin) => stdin.write_all(line.as_bytes()).await,
+        }
+    }
+}
+
+fn make_fifo(path: &Path) -> Result<()> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("failed to create {}", path.display()));
+    }
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::task::JoinHandle;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask};
+use x11rb::rust_connection::RustConnection;
+use super::XEvents;
+
+/// Named colors from `[colors]`, where entries like `xrdb:color5` are
+/// read from the X resources of the root window
+pub struct Palette {
+    colors: HashMap<String, String>,
+    resources: Arc<RwLock<HashMap<String, String>>>,
+}
+
+impl Palette {
+    pub fn new(colors: HashMap<String, String>) -> Self {
+        let palette = Self {
+            colors,
+            resources: Arc::default(),
+        };
+        if palette.uses_resources() {
+            match RustConnection::connect(None) {
+                Ok((conn, screen_num)) => {
+                    let root = conn.setup().roots[screen_num].root;
+                    *palette.resources.write().unwrap() = read_resources(&conn, root);
+                }
+                Err(e) => eprintln!("warning: could not read X resources: {e}"),
+            }
+        }
+        palette
+    }
+
+    fn uses_resources(&self) -> bool {
+        self.colors.values().any(|color| color.starts_with("xrdb:"))
+    }
+
+    /// Look a color up in the palette, leaving colors that are not in it
+    /// as they are. Returns `None` for X resources that are not set.
+    pub fn resolve(&self, color: String) -> Option<String> {
+        let Some(value) = self.colors.get(&color) else {
+            return Some(color);
+        };
+        let Some(resource) = value.strip_prefix("xrdb:") else {
+            return Some(value.clone());
+        };
+
+        // resources are usually set for every client, e.g. `*.color5`
+        let resources = self.resources.read().unwrap();
+        [resource.to_string(), format!("*.{resource}"), format!("*{resource}")]
+            .iter()
+            .find_map(|name| resources.get(name).cloned())
+    }
+
+    /// Re-read the X resources whenever `RESOURCE_MANAGER` changes, e.g.
+    /// after `xrdb -merge`, and redraw the bar
+    pub fn watch(&self, tx: UnboundedSender<()>) -> Option<JoinHandle<()>> {
+        if !self.uses_resources() {
+            return None;
+        }
+
+        let resources = Arc::clone(&self.resources);
+        Some(tokio::spawn(async move {
+            if let Err(e) = watch_resources(&resources, &tx).await {
+                eprintln!("warning: stopped watching X resources: {e}");
+            }
+        }))
+    }
+}
+
+async fn watch_resources(
+    resources: &RwLock<HashMap<String, String>>,
+    tx: &UnboundedSender<()>,
+) -> Result<()> {
+    let events = XEvents::connect()?;
+    let (conn, root) = (events.conn(), events.root());
+    conn.change_window_attributes(
+        root,
+        &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )?;
+    conn.flush()?;
+
+    loop {
+        let changed = events.wait().await?.into_iter().any(|event| {
+            matches!(event, Event::PropertyNotify(event) if event.atom == u32::from(AtomEnum::RESOURCE_MANAGER))
+        });
+        if changed {
+            *resources.write().unwrap() = read_resources(conn, root);
+            let _ = tx.send(());
+        }
+    }
+}
+
+/// Parse the `RESOURCE_MANAGER` property, made of `name:\tvalue` lines
+fn read_resources(conn: &RustConnection, root: u32) -> HashMap<String, String> {
+    let reply = conn
+        .get_property(false, root, AtomEnum::RESOURCE_MANAGER, AtomEnum::STRING, 0, u32::MAX / 4)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok());
+    let Some(reply) = reply else {
+        return HashMap::new();
+    };
+
+    String::from_utf8_lossy(&reply.value)
+        .lines()
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
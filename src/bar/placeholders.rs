@@ -0,0 +1,45 @@
+/// Replace the `{name}` placeholders of a format in one pass, so values
+/// containing braces are left as they are, and keep unknown ones
+pub fn fill<'a>(format: &str, value: impl Fn(&str) -> Option<&'a str>) -> String {
+    let mut filled = String::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        match value(&rest[start + 1..start + end]) {
+            Some(value) => {
+                filled.push_str(value);
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                filled.push('{');
+                rest = &rest[start + 1..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(name: &str) -> Option<&'static str> {
+        match name {
+            "a" => Some("{b}"),
+            "b" => Some("B"),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn fills_in_one_pass() {
+        assert_eq!(fill("{a} {b}", value), "{b} B");
+        assert_eq!(fill("{{b}}", value), "{B}");
+        assert_eq!(fill("{c} {b", value), "{c} {b");
+        assert_eq!(fill("", value), "");
+    }
+}
@@ -0,0 +1,147 @@
+use crate::config::{Powerline, SectionConfig};
+use super::width::display_width;
+
+/// Separators and caps of a section, with the global separator filled in
+pub struct SectionStyle {
+    separator: String,
+    powerline: Option<Powerline>,
+    left_cap: String,
+    right_cap: String,
+}
+
+/// A piece of a styled section, in drawing order
+pub enum Piece<'a> {
+    /// The module at this index of the section
+    Module(usize),
+    /// A separator or cap, with the colors to draw it in
+    Glue {
+        text: &'a str,
+        fg: Option<&'a str>,
+        bg: Option<&'a str>,
+    },
+}
+
+impl SectionStyle {
+    pub fn new(config: &SectionConfig, separator: &str) -> Self {
+        Self {
+            separator: config.separator.clone().unwrap_or_else(|| separator.to_string()),
+            powerline: config.powerline,
+            left_cap: config.left_cap.clone().unwrap_or_default(),
+            right_cap: config.right_cap.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Lay out the modules of a section given their backgrounds, leaving
+    /// it to the frontend to draw each piece in its colors. `separators`
+    /// replaces the separator drawn before a module, e.g. within a group.
+    pub fn layout<'a>(&'a self, backgrounds: &'a [Option<String>], separators: &[Option<&'a str>]) -> Vec<Piece<'a>> {
+        let mut pieces = Vec::new();
+        let (Some(first), Some(last)) = (backgrounds.first(), backgrounds.last()) else {
+            return pieces;
+        };
+
+        // caps sit against the bar, so only their foreground is known
+        if !self.left_cap.is_empty() {
+            pieces.push(Piece::Glue { text: &self.left_cap, fg: first.as_deref(), bg: None });
+        }
+        for (i, background) in backgrounds.iter().enumerate() {
+            let separator = self.separator_before(i, separators);
+            if i > 0 && !separator.is_empty() {
+                let previous = backgrounds[i - 1].as_deref();
+                let (fg, bg) = match self.powerline {
+                    Some(Powerline::Right) => (previous, background.as_deref()),
+                    Some(Powerline::Left) => (background.as_deref(), previous),
+                    None => (None, None),
+                };
+                pieces.push(Piece::Glue { text: separator, fg, bg });
+            }
+            pieces.push(Piece::Module(i));
+        }
+        if !self.right_cap.is_empty() {
+            pieces.push(Piece::Glue { text: &self.right_cap, fg: last.as_deref(), bg: None });
+        }
+        pieces
+    }
+
+    fn separator_before<'a>(&'a self, i: usize, separators: &[Option<&'a str>]) -> &'a str {
+        separators.get(i).copied().flatten().unwrap_or(&self.separator)
+    }
+
+    /// Columns taken by the section when its modules take `modules`
+    /// columns each
+    pub fn width(&self, modules: &[usize], separators: &[Option<&str>]) -> usize {
+        if modules.is_empty() {
+            return 0;
+        }
+        let separators: usize = (1..modules.len())
+            .map(|i| display_width(self.separator_before(i, separators)))
+            .sum();
+        modules.iter().sum::<usize>()
+            + separators
+            + display_width(&self.left_cap)
+            + display_width(&self.right_cap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(powerline: Option<Powerline>, caps: bool) -> SectionStyle {
+        let config = SectionConfig {
+            separator: Some(">".to_string()),
+            powerline,
+            left_cap: caps.then(|| "(".to_string()),
+            right_cap: caps.then(|| ")".to_string()),
+        };
+        SectionStyle::new(&config, " | ")
+    }
+
+    /// Pieces written as `0` for modules and `text:fg/bg` for glue
+    fn describe(pieces: &[Piece]) -> Vec<String> {
+        pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Module(i) => i.to_string(),
+                Piece::Glue { text, fg, bg } => format!("{text}:{}/{}", fg.unwrap_or("-"), bg.unwrap_or("-")),
+            })
+            .collect()
+    }
+
+    fn backgrounds(colors: &[Option<&str>]) -> Vec<Option<String>> {
+        colors.iter().map(|color| color.map(str::to_string)).collect()
+    }
+
+    #[test]
+    fn powerline_right_points_away_from_the_previous_module() {
+        let backgrounds = backgrounds(&[Some("a"), Some("b"), None]);
+        let style = style(Some(Powerline::Right), false);
+        assert_eq!(describe(&style.layout(&backgrounds, &[])), ["0", ">:a/b", "1", ">:b/-", "2"]);
+    }
+
+    #[test]
+    fn powerline_left_points_away_from_the_next_module() {
+        let backgrounds = backgrounds(&[Some("a"), Some("b"), None]);
+        let style = style(Some(Powerline::Left), false);
+        assert_eq!(describe(&style.layout(&backgrounds, &[])), ["0", ">:b/a", "1", ">:-/b", "2"]);
+    }
+
+    #[test]
+    fn plain_separators_and_caps() {
+        let backgrounds = backgrounds(&[Some("a"), Some("b")]);
+        let style = style(None, true);
+        assert_eq!(describe(&style.layout(&backgrounds, &[])), ["(:a/-", "0", ">:-/-", "1", "):b/-"]);
+        assert!(style.layout(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn separators_can_be_replaced_or_left_out() {
+        let backgrounds = backgrounds(&[Some("a"), Some("b"), Some("c")]);
+        let style = style(Some(Powerline::Right), false);
+        let separators = [None, Some(""), Some("+")];
+        assert_eq!(describe(&style.layout(&backgrounds, &separators)), ["0", "1", "+:b/c", "2"]);
+        assert_eq!(style.width(&[2, 3, 4], &separators), 10);
+        assert_eq!(style.width(&[2, 3, 4], &[]), 11);
+        assert_eq!(SectionStyle::new(&SectionConfig::default(), " | ").width(&[1, 1], &[]), 5);
+    }
+}
@@ -0,0 +1,112 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Blank space between the end of a scrolling value and its start
+const MARQUEE_GAP: &str = "   ";
+
+/// Width of a text, in grapheme clusters
+pub fn width(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// The first `width` grapheme clusters of a text
+pub fn cut(text: &str, width: usize) -> String {
+    text.graphemes(true).take(width).collect()
+}
+
+/// Shorten a text to `width` grapheme clusters, ending it with `…`
+/// when anything was cut
+pub fn truncate(text: &str, width: usize) -> String {
+    if self::width(text) <= width {
+        return text.to_string();
+    }
+    match width {
+        0 => String::new(),
+        width => cut(text, width - 1) + "…",
+    }
+}
+
+/// A `width` wide window into a text scrolled by `offset` grapheme
+/// clusters, wrapping around to its start
+pub fn marquee(text: &str, width: usize, offset: usize) -> String {
+    if self::width(text) <= width {
+        return text.to_string();
+    }
+    let looped: Vec<&str> = text.graphemes(true).chain(MARQUEE_GAP.graphemes(true)).collect();
+    looped
+        .iter()
+        .cycle()
+        .skip(offset % looped.len())
+        .take(width)
+        .copied()
+        .collect()
+}
+
+/// Columns a text takes on the bar, where wide characters take two and
+/// combining characters none
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Shorten a text to `columns` columns on grapheme cluster boundaries,
+/// ending it with `…` when anything was cut
+pub fn truncate_columns(text: &str, columns: usize) -> String {
+    if display_width(text) <= columns {
+        return text.to_string();
+    }
+    if columns == 0 {
+        return String::new();
+    }
+
+    let mut result = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let width = grapheme.width();
+        // keep a column for the ellipsis
+        if used + width > columns - 1 {
+            break;
+        }
+        result.push_str(grapheme);
+        used += width;
+    }
+    result + "…"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_on_graphemes() {
+        assert_eq!(truncate("hello", 5), "hello");
+        assert_eq!(truncate("hello", 4), "hel…");
+        assert_eq!(truncate("hello", 0), "");
+        // e + combining acute accent is one grapheme
+        assert_eq!(truncate("e\u{301}e\u{301}e\u{301}", 2), "e\u{301}…");
+        assert_eq!(cut("🇫🇷🇩🇪", 1), "🇫🇷");
+    }
+
+    #[test]
+    fn truncate_columns_counts_wide_characters() {
+        assert_eq!(display_width("日本語"), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(truncate_columns("日本語", 6), "日本語");
+        assert_eq!(truncate_columns("日本語", 5), "日本…");
+        // a wide character that does not fit is left out whole
+        assert_eq!(truncate_columns("日本語", 4), "日…");
+        assert_eq!(truncate_columns("日本語", 0), "");
+        assert_eq!(truncate_columns("e\u{301}bc", 2), "e\u{301}…");
+    }
+
+    #[test]
+    fn marquee_wraps_around() {
+        assert_eq!(marquee("abc", 3, 2), "abc");
+        assert_eq!(marquee("abcdef", 3, 0), "abc");
+        assert_eq!(marquee("abcdef", 3, 4), "ef ");
+        assert_eq!(marquee("abcdef", 3, 8), " ab");
+        // the value and the gap loop every 9 steps
+        assert_eq!(marquee("abcdef", 3, 10), marquee("abcdef", 3, 1));
+        assert_eq!(marquee("e\u{301}bcd", 2, 4), "  ");
+        assert_eq!(marquee("e\u{301}bcd", 2, 6), " e\u{301}");
+    }
+}
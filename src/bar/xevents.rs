@@ -0,0 +1,80 @@
+use anyhow::Result;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use tokio::io::unix::AsyncFd;
+use x11rb::connection::Connection;
+use x11rb::protocol::Event;
+use x11rb::protocol::xproto::{ChangeWindowAttributesAux, ConnectionExt, EventMask, Window};
+use x11rb::rust_connection::RustConnection;
+
+/// An X connection whose events are awaited on its socket instead of
+/// blocking a runtime thread, so the task reading them can be stopped
+/// on reload
+pub struct XEvents {
+    fd: AsyncFd<XConnection>,
+    root: Window,
+}
+
+/// The connection registered with the runtime, owned so that its socket
+/// stays open for as long as it is registered
+struct XConnection(RustConnection);
+
+impl AsRawFd for XConnection {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.stream().as_raw_fd()
+    }
+}
+
+impl AsFd for XConnection {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.stream().as_fd()
+    }
+}
+
+impl XEvents {
+    /// Connect to the display of `$DISPLAY`
+    pub fn connect() -> Result<Self> {
+        let (conn, screen_num) = RustConnection::connect(None)?;
+        let root = conn.setup().roots[screen_num].root;
+        // SAFETY: the connection owns its socket, which stays open and is
+        // returned by every `as_raw_fd` call until the connection is
+        // dropped along with the `AsyncFd`
+        let fd = unsafe { AsyncFd::register(XConnection(conn)) }.map_err(|e| e.into_parts().1)?;
+        Ok(Self { fd, root })
+    }
+
+    /// Connect and listen to the events of the root window in `event_mask`
+    pub fn watch_root(event_mask: EventMask) -> Result<Self> {
+        let events = Self::connect()?;
+        events.conn().change_window_attributes(
+            events.root,
+            &ChangeWindowAttributesAux::new().event_mask(event_mask),
+        )?;
+        events.conn().flush()?;
+        Ok(events)
+    }
+
+    pub fn conn(&self) -> &RustConnection {
+        &self.fd.get_ref().0
+    }
+
+    /// Root window of the default screen
+    pub fn root(&self) -> Window {
+        self.root
+    }
+
+    /// Wait until events arrive and return them all
+    pub async fn wait(&self) -> Result<Vec<Event>> {
+        loop {
+            // events may already have been read along with a reply
+            let mut events = Vec::new();
+            while let Some(event) = self.conn().poll_for_event()? {
+                events.push(event);
+            }
+            if !events.is_empty() {
+                return Ok(events);
+            }
+
+            self.fd.readable().await?.clear_ready();
+        }
+    }
+}
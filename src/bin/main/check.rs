@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+use barrs::{Config, Severity};
+
+/// Check a config file, the one barrs would load when none is given,
+/// and print every problem found
+pub fn run(path: Option<&str>, profile: Option<&str>) -> bool {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => match Config::path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("error: {e}");
+                return false;
+            }
+        },
+    };
+
+    let diagnostics = Config::check(&path, profile);
+    for diagnostic in &diagnostics {
+        eprintln!("{diagnostic}");
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = diagnostics.len() - errors;
+    if diagnostics.is_empty() {
+        println!("{}: ok", path.display());
+    } else {
+        eprintln!("{}: {errors} error(s), {warnings} warning(s)", path.display());
+    }
+    errors == 0
+}
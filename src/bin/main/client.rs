@@ -0,0 +1,249 @@
+use serde_json::Value;
+
+use barrs::bar::ModuleInfo;
+use barrs::ipc::{self, Request, Response};
+
+pub fn print_usage(executable_name: &str) {
+    eprintln!(
+        "error: usage:\n\
+        {executable_name} [-i|--instance <name>] [-p|--profile <name>]\n\
+        {executable_name} [-i|--instance <name>|--all] <command>\n\
+        \n\
+        commands:\n  \
+        check [path]\n  \
+        update <target>\n  \
+        list [--json]\n  \
+        get <module> [--json]\n  \
+        set <module> <text> [--color <color>] [--expire <seconds>]\n  \
+        click <module> <button>\n  \
+        toggle <target>\n  \
+        expand <group>\n  \
+        collapse <group>\n  \
+        hide <target>\n  \
+        show <target>\n  \
+        reload\n  \
+        quit"
+    );
+}
+
+/// Remove `--name <value>` from the arguments and return the value
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<Option<String>> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Some(None);
+    };
+    if index + 1 >= args.len() {
+        return None;
+    }
+    let value = args.remove(index + 1);
+    args.remove(index);
+    Some(Some(value))
+}
+
+/// Parse a client command from the command line
+pub fn parse_request(mut args: Vec<String>) -> Option<Request> {
+    let color = take_option(&mut args, "--color")?;
+    let expire = match take_option(&mut args, "--expire")? {
+        Some(expire) => Some(expire.parse().ok()?),
+        None => None,
+    };
+
+    let target = || args.get(1).cloned();
+    let request = match args.first()?.as_str() {
+        "update" => Request::Update { target: target()? },
+        "list" => Request::List,
+        "get" => Request::Get { target: target()? },
+        "hide" => Request::Hide { target: target()? },
+        "show" => Request::Show { target: target()? },
+        "set" => Request::Set {
+            target: target()?,
+            text: args.get(2)?.clone(),
+            color,
+            expire,
+        },
+        "toggle" => Request::Toggle { target: target()? },
+        "expand" => Request::Expand { target: target()? },
+        "collapse" => Request::Collapse { target: target()? },
+        "click" => Request::Click {
+            target: target()?,
+            button: args.get(2)?.parse().ok()?,
+        },
+        "reload" => Request::Reload,
+        "quit" => Request::Quit,
+        _ => return None,
+    };
+
+    let expected_len = match request {
+        Request::Reload | Request::Quit | Request::List => 1,
+        Request::Set { .. } | Request::Click { .. } => 3,
+        _ => 2,
+    };
+    (args.len() == expected_len).then_some(request)
+}
+
+/// Send a request to one instance, or to every running instance with
+/// `all`, and print the result
+///
+/// Without an instance, `update` is sent to every running instance and
+/// other requests to the default one. When sent to every instance, the
+/// request only fails if no instance accepted it, since modules are
+/// usually only loaded by some bars.
+pub async fn run(instance: Option<&str>, all: bool, request: &Request, json: bool) -> bool {
+    let targets = match instance {
+        None if all || matches!(request, Request::Update { .. }) => ipc::instance_sockets(),
+        instance => {
+            let instance = instance.unwrap_or(ipc::DEFAULT_INSTANCE);
+            match ipc::socket_path(instance) {
+                Ok(path) => vec![(instance.to_string(), path)],
+                Err(e) => {
+                    eprintln!("error: {e:#}");
+                    return false;
+                }
+            }
+        }
+    };
+
+    if targets.is_empty() {
+        eprintln!("error: no running instance found");
+        return false;
+    }
+
+    let mut replies = Vec::new();
+    let mut errors = Vec::new();
+    for (name, path) in targets {
+        match ipc::send(&path, request).await {
+            Ok(Response::Ok { data }) => replies.push((name, data)),
+            Ok(Response::Error { message }) => errors.push(format!("{name}: {message}")),
+            Err(e) => errors.push(format!("{name}: {e:#}")),
+        }
+    }
+
+    if replies.is_empty() {
+        for error in &errors {
+            eprintln!("error: {error}");
+        }
+        return false;
+    }
+
+    match request {
+        Request::List => print_list(replies, json),
+        // the first instance that has the module answers
+        Request::Get { .. } => print_output(&replies[0].1, json),
+        _ => {}
+    }
+    true
+}
+
+fn print_json(value: &Value) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{json}"),
+        Err(e) => eprintln!("error: {e}"),
+    }
+}
+
+fn print_list(replies: Vec<(String, Value)>, json: bool) {
+    let several_bars = replies.len() > 1;
+    let mut modules: Vec<(String, ModuleInfo)> = Vec::new();
+    for (bar, data) in replies {
+        match serde_json::from_value::<Vec<ModuleInfo>>(data) {
+            Ok(infos) => modules.extend(infos.into_iter().map(|info| (bar.clone(), info))),
+            Err(e) => eprintln!("error: {bar}: invalid reply: {e}"),
+        }
+    }
+
+    if json {
+        let values = modules
+            .into_iter()
+            .filter_map(|(bar, info)| {
+                let mut value = serde_json::to_value(info).ok()?;
+                value["bar"] = Value::String(bar);
+                Some(value)
+            })
+            .collect();
+        print_json(&Value::Array(values));
+        return;
+    }
+
+    let optional = |value: Option<String>| value.unwrap_or_else(|| "-".to_string());
+    let mut rows = vec![vec![
+        "BAR", "NAME", "MODULE", "INSTANCE", "SECTION", "INTERVAL", "SIGNAL_ID", "SIGNAL", "GROUPS",
+    ].into_iter().map(String::from).collect::<Vec<_>>()];
+    for (bar, info) in modules {
+        let mut name = info.name;
+        if info.hidden {
+            name.push_str(" (hidden)");
+        }
+        rows.push(vec![
+            bar,
+            name,
+            info.module,
+            optional(info.instance),
+            info.section,
+            optional(info.interval.map(|i| format!("{i}s"))),
+            optional(info.signal_id.map(|id| id.to_string())),
+            optional(info.signal.map(|n| format!("RTMIN+{n}"))),
+            if info.groups.is_empty() { "-".to_string() } else { info.groups.join(",") },
+        ]);
+    }
+
+    // the bar column is only useful when several instances answered
+    if !several_bars {
+        for row in &mut rows {
+            row.remove(0);
+        }
+    }
+
+    let columns = rows[0].len();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| rows.iter().map(|row| row[i].chars().count()).max().unwrap_or(0))
+        .collect();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
+
+fn print_output(data: &Value, json: bool) {
+    if json {
+        print_json(data);
+    } else {
+        println!("{}", data["value"].as_str().unwrap_or_default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Option<Request> {
+        parse_request(args.split_whitespace().map(str::to_string).collect())
+    }
+
+    #[test]
+    fn parses_commands() {
+        assert!(matches!(parse("update battery"), Some(Request::Update { target }) if target == "battery"));
+        assert!(matches!(parse("list"), Some(Request::List)));
+        assert!(matches!(
+            parse("set ipc#vpn up --expire 5 --color red"),
+            Some(Request::Set { target, text, color: Some(color), expire: Some(5) })
+                if target == "ipc#vpn" && text == "up" && color == "red"
+        ));
+        assert!(matches!(parse("click date 3"), Some(Request::Click { button: 3, .. })));
+    }
+
+    #[test]
+    fn rejects_malformed_commands() {
+        assert!(parse("").is_none());
+        assert!(parse("restart").is_none());
+        assert!(parse("update").is_none());
+        assert!(parse("update a b").is_none());
+        assert!(parse("quit now").is_none());
+        assert!(parse("click date left").is_none());
+        assert!(parse("click date 256").is_none());
+        assert!(parse("set ipc up --expire").is_none());
+        assert!(parse("set ipc up --expire soon").is_none());
+    }
+}
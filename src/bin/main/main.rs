@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use tokio::signal::unix::{SignalKind, signal};
+use tokio::sync::mpsc;
+
+use barrs::Config;
+use barrs::Bar;
+use barrs::bar::{Frame, Output};
+use barrs::OutputConfig;
+use barrs::ipc::{self, Request, Response, Server};
+
+mod check;
+mod client;
+
+/// Resolves once SIGINT, SIGTERM or SIGHUP is received
+fn shutdown_signal() -> std::io::Result<impl Future<Output = ()>> {
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    Ok(async move {
+        tokio::select! {
+            _ = interrupt.recv() => {}
+            _ = terminate.recv() => {}
+            _ = hangup.recv() => {}
+        }
+    })
+}
+
+/// Open the outputs of the bars that are not open yet, and close those
+/// no bar writes to anymore, e.g. after a reload
+fn open_outputs(outputs: &mut HashMap<OutputConfig, Output>, bar: &Bar) {
+    let wanted = bar.outputs();
+    outputs.retain(|output, _| wanted.contains(&output));
+    for output in wanted {
+        if outputs.contains_key(output) {
+            continue;
+        }
+        match Output::open(output) {
+            Ok(opened) => {
+                outputs.insert(output.clone(), opened);
+            }
+            Err(e) => eprintln!("error: {e:#}"),
+        }
+    }
+}
+
+/// Write each frame to the output of its bar, closing the outputs that
+/// fail since whatever read them has gone away
+async fn write_frames(outputs: &mut HashMap<OutputConfig, Output>, frames: Vec<Frame>) {
+    for frame in frames {
+        let Some(output) = outputs.get_mut(&frame.output) else {
+            continue;
+        };
+        if let Err(e) = output.write_frame(&frame.text).await {
+            if frame.output != OutputConfig::Stdout {
+                eprintln!("warning: stopped writing to {}: {e}", frame.output);
+            }
+            outputs.remove(&frame.output);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args: Vec<String> = std::env::args().collect();
+    let executable_name = args.remove(0);
+
+    // options are accepted before the command
+    let mut instance: Option<String> = None;
+    let mut profile: Option<String> = None;
+    while let Some(arg) = args.first() {
+        match arg.as_str() {
+            "-i" | "--instance" if args.len() > 1 => {
+                instance = Some(args[1].clone());
+                args.drain(..2);
+            }
+            "-p" | "--profile" if args.len() > 1 => {
+                profile = Some(args[1].clone());
+                args.drain(..2);
+            }
+            _ => break,
+        }
+    }
+
+    if args.first().is_some_and(|arg| arg == "check") && args.len() <= 2 {
+        let ok = check::run(args.get(1).map(String::as_str), profile.as_deref());
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    // handle client commands
+    if !args.is_empty() {
+        let json_len = args.len();
+        args.retain(|arg| arg != "--json");
+        let json = args.len() != json_len;
+
+        let all_len = args.len();
+        args.retain(|arg| arg != "--all");
+        let all = args.len() != all_len;
+
+        let request = client::parse_request(args).filter(|_| !(all && instance.is_some()));
+        let Some(request) = request else {
+            client::print_usage(&executable_name);
+            std::process::exit(1);
+        };
+        if !client::run(instance.as_deref(), all, &request, json).await {
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let load_config = || Config::load(&Config::path()?, profile.as_deref());
+    let mut config = match load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("error: {e}");
+            return;
+        }
+    };
+    let instance = instance.unwrap_or_else(|| config.instance.clone());
+    config.instance = instance.clone();
+
+    let shutdown = match shutdown_signal() {
+        Ok(shutdown) => shutdown,
+        Err(e) => {
+            eprintln!("error: could not install signal handlers: {e}");
+            std::process::exit(1);
+        }
+    };
+    tokio::pin!(shutdown);
+
+    let (server, mut requests) = match ipc::prepare_socket_dir()
+        .and_then(|()| Server::bind(&ipc::socket_path(&instance)?))
+    {
+        Ok(server) => server,
+        Err(e) => {
+            eprintln!("error: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut bar = Bar::new(&config, tx.clone());
+    bar.start_modules();
+
+    let mut outputs = HashMap::new();
+    open_outputs(&mut outputs, &bar);
+    let mut scrolled = false;
+    'main: loop {
+        let frames = if scrolled { bar.construct_scrolled().await } else { bar.construct().await };
+        write_frames(&mut outputs, frames).await;
+        // stop when every bar reading our output has gone away
+        if outputs.is_empty() {
+            break;
+        }
+
+        // wait for any module to send update signal or a command
+        scrolled = false;
+        tokio::select! {
+            update = rx.recv() => if update.is_none() { break },
+            _ = bar.scroll_tick() => scrolled = true,
+            Some((request, reply)) = requests.recv() => {
+                let response = match request {
+                    Request::Update { target } => bar.update(&target).into(),
+                    Request::List => Response::data(bar.list()),
+                    Request::Get { target } => match bar.get(&target).await {
+                        Ok(output) => Response::data(output),
+                        Err(e) => Response::error(e.to_string()),
+                    },
+                    Request::Hide { target } => bar.hide(&target).into(),
+                    Request::Show { target } => bar.show(&target).into(),
+                    Request::Set { target, text, color, expire } => {
+                        bar.set(&target, &text, color.as_deref(), expire).into()
+                    }
+                    Request::Toggle { target } => bar.toggle(&target).into(),
+                    Request::Expand { target } => bar.set_expanded(&target, true).into(),
+                    Request::Collapse { target } => bar.set_expanded(&target, false).into(),
+                    Request::Click { target, button } => bar.click(&target, button).into(),
+                    Request::Reload => match load_config() {
+                        Ok(mut config) => {
+                            config.instance = instance.clone();
+                            bar.shutdown();
+                            bar = Bar::new(&config, tx.clone());
+                            bar.start_modules();
+                            open_outputs(&mut outputs, &bar);
+                            Response::ok()
+                        }
+                        Err(e) => Response::error(format!("{e:#}")),
+                    },
+                    Request::Quit => {
+                        let _ = reply.send(Response::ok());
+                        break 'main;
+                    }
+                };
+                let _ = reply.send(response);
+            }
+            _ = &mut shutdown => break,
+        }
+    }
+
+    bar.shutdown();
+    drop(requests);
+    server.shutdown().await;
+    for output in outputs.values_mut() {
+        let _ = output.write_frame("").await;
+    }
+
+    // exit instead of waiting for the runtime to wind down the aborted
+    // module tasks
+    std::process::exit(0);
+}
@@ -0,0 +1,939 @@
+use serde::de::{self, DeserializeOwned, Deserializer, Visitor};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use toml::de::{DeTable, DeValue};
+use super::*;
+use super::parse::{FORMAT_VARIANTS, load_table, variant_key};
+
+/// How serious a problem found by [`Config::check`] is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found by [`Config::check`], located in the config file
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// File the problem is in, which may be an included file
+    pub file: PathBuf,
+    /// 1-based line of the problem
+    pub line: usize,
+    /// 1-based column of the problem, in characters
+    pub column: usize,
+    pub message: String,
+    /// A suggestion to fix the problem, e.g. the closest valid name
+    pub help: Option<String>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}:{}:{}: {severity}: {}", self.file.display(), self.line, self.column, self.message)?;
+        if let Some(help) = &self.help {
+            write!(f, "\n  help: {help}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Module kinds built into barrs
+const KINDS: &[&str] = &[
+    "battery", "brightnessctl", "command", "cpu", "cputemp", "date", "external", "filesystem",
+    "ipc", "kernel", "loadavg", "memory", "network", "stream", "uptime", "wpctl", "xkeyboard",
+    "xwindow", "xworkspaces",
+];
+
+const FRONTENDS: &[&str] = &["lemonbar"];
+
+const SECTIONS: &[&str] = &["left", "center", "right"];
+
+const POWERLINE_DIRECTIONS: &[&str] = &["left", "right"];
+
+const OUTPUTS: &[&str] = &["stdout", "pipe", "command"];
+
+const ERROR_PLACEHOLDERS: &[&str] = &["error", "kind", "module"];
+
+const FILESYSTEM_PLACEHOLDERS: &[&str] = &[
+    "mount", "%_free", "%_used",
+    "kb_free", "kb_total", "kb_used", "kib_free", "kib_total", "kib_used",
+    "mb_free", "mb_total", "mb_used", "mib_free", "mib_total", "mib_used",
+    "gb_free", "gb_total", "gb_used", "gib_free", "gib_total", "gib_used",
+    "tb_free", "tb_total", "tb_used", "tib_free", "tib_total", "tib_used",
+];
+
+const NETWORK_PLACEHOLDERS: &[&str] = &[
+    "iface", "ip", "state",
+    "down_speed_bit", "down_speed_kbit", "down_speed_mbit", "down_speed_gbit",
+    "up_speed_bit", "up_speed_kbit", "up_speed_mbit", "up_speed_gbit",
+];
+
+const UPTIME_PLACEHOLDERS: &[&str] = &[
+    "days", "hours", "minutes", "seconds",
+    "total_days", "total_hours", "total_minutes", "total_seconds",
+];
+
+/// Keys a module kind accepts and the placeholders of its formats,
+/// `None` when any placeholder is valid
+fn module_schema(kind: &str) -> Option<(&'static [&'static str], Option<&'static [&'static str]>)> {
+    Some(match kind {
+        "battery" =>       (struct_fields::<BatteryConfig>(),       Some(&["%", "status", "time"])),
+        "brightnessctl" => (struct_fields::<BrightnessctlConfig>(), Some(&["%"])),
+        "command" =>       (struct_fields::<CommandConfig>(),       Some(&["stdout", "exit_code"])),
+        "cpu" =>           (struct_fields::<CpuConfig>(),           Some(&["%"])),
+        "cputemp" =>       (struct_fields::<CputempConfig>(),       Some(&["c", "f", "k"])),
+        // formatted with strftime
+        "date" =>          (struct_fields::<DateConfig>(),          None),
+        "external" =>      (struct_fields::<ExternalConfig>(),      None),
+        "filesystem" =>    (struct_fields::<FilesystemConfig>(),    Some(FILESYSTEM_PLACEHOLDERS)),
+        "ipc" =>           (struct_fields::<IpcConfig>(),           Some(&[])),
+        "kernel" =>        (struct_fields::<KernelConfig>(),        Some(&["ostype", "hostname", "osrelease", "domainname"])),
+        "loadavg" =>       (struct_fields::<LoadavgConfig>(),       Some(&["1m", "5m", "15m", "total"])),
+        "memory" =>        (struct_fields::<MemoryConfig>(),        Some(&["%", "mb", "mib", "gb", "gib"])),
+        "network" =>       (struct_fields::<NetworkConfig>(),       Some(NETWORK_PLACEHOLDERS)),
+        "stream" =>        (struct_fields::<StreamConfig>(),        None),
+        "uptime" =>        (struct_fields::<UptimeConfig>(),        Some(UPTIME_PLACEHOLDERS)),
+        "wpctl" =>         (struct_fields::<WpctlConfig>(),         Some(&["volume"])),
+        "xkeyboard" =>     (struct_fields::<XkeyboardConfig>(),     Some(&[])),
+        "xwindow" =>       (struct_fields::<XwindowConfig>(),       Some(&[])),
+        "xworkspaces" =>   (struct_fields::<XworkspacesConfig>(),   Some(&["name", "index"])),
+        _ => return None,
+    })
+}
+
+/// Keys that were renamed, with their new name
+const DEPRECATED_KEYS: &[(&str, &str)] = &[("sepparator", "separator"), ("max_length", "max_width")];
+
+impl Config {
+    /// Validate a config file and the files it includes without
+    /// starting any module, returning every problem found
+    ///
+    /// `profile` is applied as in [`Config::load`] before checking the
+    /// modules used in the sections.
+    pub fn check(path: &Path, profile: Option<&str>) -> Vec<Diagnostic> {
+        let mut findings = Findings::default();
+        if let Ok(table) = load_table(path, profile)
+            && let Some(toml::Value::Table(colors)) = table.get("colors")
+        {
+            findings.palette = colors.keys().cloned().collect();
+        }
+        let contents = fs::read_to_string(path).unwrap_or_default();
+        let mut checker = Checker {
+            path,
+            source: &contents,
+            findings: &mut findings,
+        };
+
+        match checker.check_file(&mut Vec::new()) {
+            Ok(()) => checker.check_merged(profile),
+            Err(message) => checker.error(0..0, message, None),
+        }
+
+        let mut diagnostics = findings.diagnostics;
+        diagnostics.sort_by(|a, b| (&a.file, a.line, a.column).cmp(&(&b.file, b.line, b.column)));
+        diagnostics
+    }
+}
+
+/// Where a problem is, for problems found after merging all files
+#[derive(Clone)]
+struct Location {
+    file: PathBuf,
+    line: usize,
+    column: usize,
+}
+
+/// What the checkers of a file and of the files it includes found
+#[derive(Default)]
+struct Findings {
+    diagnostics: Vec<Diagnostic>,
+    /// Where each module of a section was first listed
+    module_locations: HashMap<String, Location>,
+    /// Whether a file is not valid TOML, making the merged config
+    /// unavailable
+    syntax_error: bool,
+    /// Names of the `[colors]` palette
+    palette: HashSet<String>,
+    /// Names of the groups of the sections, which may be styled like
+    /// modules
+    groups: HashSet<String>,
+}
+
+/// Checks one config file
+struct Checker<'a> {
+    path: &'a Path,
+    source: &'a str,
+    findings: &'a mut Findings,
+}
+
+impl Checker<'_> {
+    fn locate(&self, span: Range<usize>) -> Location {
+        let before = &self.source[..span.start.min(self.source.len())];
+        Location {
+            file: self.path.to_path_buf(),
+            line: before.matches('\n').count() + 1,
+            column: before.rsplit('\n').next().unwrap_or("").chars().count() + 1,
+        }
+    }
+
+    fn report_at(&mut self, location: Location, severity: Severity, message: String, help: Option<String>) {
+        self.findings.diagnostics.push(Diagnostic {
+            severity,
+            file: location.file,
+            line: location.line,
+            column: location.column,
+            message,
+            help,
+        });
+    }
+
+    fn report(&mut self, severity: Severity, span: Range<usize>, message: String, help: Option<String>) {
+        let location = self.locate(span);
+        self.report_at(location, severity, message, help);
+    }
+
+    fn error(&mut self, span: Range<usize>, message: String, help: Option<String>) {
+        self.report(Severity::Error, span, message, help);
+    }
+
+    /// Check the keys of this file and of the files it includes,
+    /// failing if the file itself cannot be read
+    fn check_file(&mut self, stack: &mut Vec<PathBuf>) -> Result<(), String> {
+        let canonical = self
+            .path
+            .canonicalize()
+            .map_err(|e| format!("failed to read {}: {e}", self.path.display()))?;
+        if stack.contains(&canonical) {
+            return Err(format!("{} includes itself", self.path.display()));
+        }
+
+        let root = match DeTable::parse(self.source) {
+            Ok(root) => root,
+            Err(e) => {
+                self.error(e.span().unwrap_or_default(), e.message().to_string(), None);
+                self.findings.syntax_error = true;
+                return Ok(());
+            }
+        };
+
+        stack.push(canonical);
+        self.check_table(root.get_ref(), Some(stack));
+        stack.pop();
+        Ok(())
+    }
+
+    /// Check the top-level keys of a file, or of a profile when `stack`
+    /// is `None` since profiles cannot include files
+    fn check_table(&mut self, root: &DeTable, mut stack: Option<&mut Vec<PathBuf>>) {
+        let config_fields = struct_fields::<Config>();
+        let mut known = config_fields.to_vec();
+        if stack.is_some() {
+            known.extend(["include", "profile"]);
+        }
+
+        // groups are named in the sections but styled in `[modules]`
+        self.collect_groups(root);
+        if let Some(DeValue::Table(bars)) = root.get("bars").map(|bars| bars.get_ref()) {
+            for (_, bar) in bars.iter() {
+                if let DeValue::Table(bar) = bar.get_ref() {
+                    self.collect_groups(bar);
+                }
+            }
+        }
+
+        for (key, value) in root.iter() {
+            let key_name: &str = key.get_ref();
+            if !known.contains(&key_name) {
+                self.error(key.span(), format!("unknown key `{key_name}`"), did_you_mean(key_name, &known));
+                continue;
+            }
+
+            match (key_name, value.get_ref()) {
+                ("error_color", DeValue::String(color)) => self.check_color(color, value.span()),
+                ("error_format", DeValue::String(format)) => {
+                    self.check_placeholders(format, ERROR_PLACEHOLDERS, value.span());
+                }
+                ("bars", DeValue::Table(bars)) => self.check_bars(bars),
+                ("colors", DeValue::Table(colors)) => {
+                    for (_, color) in colors.iter() {
+                        match color.get_ref() {
+                            DeValue::String(text)
+                                if is_color(text) || is_interpolated(text) || is_resource(text) => {}
+                            _ => self.error(
+                                color.span(),
+                                "palette colors must be written #RGB, #RRGGBB or #AARRGGBB, or xrdb:<resource>"
+                                    .to_string(),
+                                None,
+                            ),
+                        }
+                    }
+                }
+                ("defaults", DeValue::Table(defaults)) => self.check_defaults(defaults),
+                ("modules", DeValue::Table(modules)) => {
+                    for (name, table) in modules.iter() {
+                        self.check_module_table(name.get_ref(), name.span(), table.get_ref());
+                    }
+                }
+                ("include", _) => {
+                    if let Some(stack) = stack.as_deref_mut() {
+                        self.check_includes(value, stack);
+                    }
+                }
+                ("profile", DeValue::Table(profiles)) => {
+                    for (name, profile) in profiles.iter() {
+                        match profile.get_ref().as_table() {
+                            Some(profile) => self.check_table(profile, None),
+                            None => self.error(name.span(), format!("profile `{}` must be a table", name.get_ref()), None),
+                        }
+                    }
+                }
+                _ => self.check_bar_key(key_name, value),
+            }
+        }
+    }
+
+    /// Check a key that sets up a bar, either the top-level one or one
+    /// under `[bars]`
+    fn check_bar_key(&mut self, key_name: &str, value: &toml::Spanned<DeValue>) {
+        match (key_name, value.get_ref()) {
+            ("frontend", DeValue::String(frontend)) if !FRONTENDS.contains(&frontend.as_ref()) => {
+                self.report(
+                    Severity::Warning,
+                    value.span(),
+                    format!("unknown frontend `{frontend}`, plain text will be printed"),
+                    did_you_mean(frontend, FRONTENDS),
+                );
+            }
+            ("left" | "center" | "right", DeValue::Array(items)) => {
+                for item in items.iter() {
+                    match item.get_ref() {
+                        DeValue::Table(group) => self.check_group(group),
+                        _ => self.record_module(item),
+                    }
+                }
+            }
+            ("sections", DeValue::Table(sections)) => self.check_sections(sections),
+            ("output", _) => self.check_output(value),
+            _ => {}
+        }
+    }
+
+    fn check_bars(&mut self, bars: &DeTable) {
+        let fields = struct_fields::<BarConfig>();
+        for (name, bar) in bars.iter() {
+            let bar_name: &str = name.get_ref();
+            let Some(table) = bar.get_ref().as_table() else {
+                self.error(name.span(), format!("bar `{bar_name}` must be a table"), None);
+                continue;
+            };
+            for (key, value) in table.iter() {
+                let key_name: &str = key.get_ref();
+                if !fields.contains(&key_name) {
+                    self.error(key.span(), format!("unknown key `{key_name}` in bar `{bar_name}`"), did_you_mean(key_name, fields));
+                    continue;
+                }
+                self.check_bar_key(key_name, value);
+            }
+        }
+    }
+
+    fn check_output(&mut self, output: &toml::Spanned<DeValue>) {
+        let help = || Some("outputs are `stdout`, `{ pipe = \"<path>\" }` or `{ command = \"<command>\" }`".to_string());
+        match output.get_ref() {
+            DeValue::String(name) if name.as_ref() == "stdout" => {}
+            DeValue::String(name) => {
+                self.error(output.span(), format!("unknown output `{name}`"), did_you_mean(name, OUTPUTS).or_else(help));
+            }
+            DeValue::Table(table) if table.len() == 1 => {
+                for (key, value) in table.iter() {
+                    let key_name: &str = key.get_ref();
+                    if !matches!(key_name, "pipe" | "command") {
+                        self.error(key.span(), format!("unknown output `{key_name}`"), did_you_mean(key_name, OUTPUTS).or_else(help));
+                    } else if !matches!(value.get_ref(), DeValue::String(_)) {
+                        self.error(value.span(), format!("`{key_name}` must be a string"), None);
+                    }
+                }
+            }
+            _ => self.error(output.span(), "invalid output".to_string(), help()),
+        }
+    }
+
+    /// Remember the names of the groups of a file or bar's sections
+    fn collect_groups(&mut self, table: &DeTable) {
+        for section in SECTIONS {
+            let Some(DeValue::Array(items)) = table.get(*section).map(|items| items.get_ref()) else {
+                continue;
+            };
+            for item in items.iter() {
+                if let DeValue::Table(group) = item.get_ref()
+                    && let Some(DeValue::String(name)) = group.get("group").map(|name| name.get_ref())
+                {
+                    self.findings.groups.insert(name.to_string());
+                }
+            }
+        }
+    }
+
+    fn check_includes(&mut self, includes: &toml::Spanned<DeValue>, stack: &mut Vec<PathBuf>) {
+        let Some(includes) = includes.get_ref().as_array() else {
+            self.error(includes.span(), "`include` must be a list of paths".to_string(), None);
+            return;
+        };
+
+        for include in includes.iter() {
+            let Some(file) = include.get_ref().as_str() else {
+                self.error(include.span(), "`include` must be a list of paths".to_string(), None);
+                continue;
+            };
+            let path = self.path.parent().unwrap_or(Path::new("")).join(file);
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    self.error(include.span(), format!("failed to read {}: {e}", path.display()), None);
+                    continue;
+                }
+            };
+
+            let mut checker = Checker {
+                path: &path,
+                source: &contents,
+                findings: self.findings,
+            };
+            if let Err(message) = checker.check_file(stack) {
+                self.error(include.span(), message, None);
+            }
+        }
+    }
+
+    /// Deserialize the merged config and every module used in a
+    /// section the way the bar does
+    fn check_merged(&mut self, profile: Option<&str>) {
+        // syntax errors were already reported with their location
+        if self.findings.syntax_error {
+            return;
+        }
+        let table = match load_table(self.path, profile) {
+            Ok(table) => table,
+            Err(e) => {
+                self.error(0..0, format!("{e:#}"), None);
+                return;
+            }
+        };
+
+        match toml::Value::Table(table.clone()).try_into::<Config>() {
+            Ok(config) => self.check_layouts(&config),
+            Err(e) => self.error(0..0, e.message().to_string(), None),
+        }
+
+        let mut module_config = table
+            .get("modules")
+            .cloned()
+            .and_then(|modules| modules.try_into::<ModuleConfig>().ok())
+            .unwrap_or_default();
+        if let Some(toml::Value::Table(defaults)) = table.get("defaults") {
+            module_config.defaults = defaults.clone();
+        }
+        let mut bars = vec![&table];
+        if let Some(toml::Value::Table(named)) = table.get("bars") {
+            bars.extend(named.values().filter_map(toml::Value::as_table));
+        }
+        let mut seen = HashSet::new();
+        for (bar, section) in bars.iter().flat_map(|bar| SECTIONS.iter().map(move |section| (bar, section))) {
+            let Some(toml::Value::Array(items)) = bar.get(*section) else {
+                continue;
+            };
+            let names = items.iter().flat_map(|item| match item {
+                toml::Value::Table(group) => match group.get("modules") {
+                    Some(toml::Value::Array(names)) => names.iter().collect(),
+                    _ => Vec::new(),
+                },
+                name => vec![name],
+            });
+            for name in names.filter_map(toml::Value::as_str) {
+                if seen.insert(name) {
+                    let location = match self.findings.module_locations.get(name) {
+                        Some(location) => location.clone(),
+                        None => self.locate(0..0),
+                    };
+                    self.check_module(name, location, &module_config);
+                }
+            }
+        }
+    }
+
+    /// Warn about bars written to the same output, whose frames would
+    /// take turns on a single bar, and about bars drawn per monitor by
+    /// frontends without monitors
+    fn check_layouts(&mut self, config: &Config) {
+        let mut writers: Vec<(&OutputConfig, Vec<String>)> = Vec::new();
+        let layouts = config.layouts();
+        for (name, bar) in &layouts {
+            let name = name.map_or_else(|| "the top-level bar".to_string(), |name| format!("`{name}`"));
+            let frontend = bar.frontend.as_ref().unwrap_or(&config.frontend);
+            if bar.per_monitor.unwrap_or(config.per_monitor) && frontend != "lemonbar" {
+                self.report(
+                    Severity::Warning,
+                    0..0,
+                    format!("{name} is drawn once, as `per_monitor` needs the lemonbar frontend"),
+                    None,
+                );
+            }
+            match writers.iter_mut().find(|(output, _)| *output == &bar.output) {
+                Some((_, names)) => names.push(name),
+                None => writers.push((&bar.output, vec![name])),
+            }
+        }
+        for (output, names) in writers {
+            if names.len() > 1 {
+                self.report(
+                    Severity::Warning,
+                    0..0,
+                    format!("{} all write to {output}", names.join(", ")),
+                    Some("give each bar its own `output`".to_string()),
+                );
+            }
+        }
+    }
+
+    fn check_module(&mut self, name: &str, location: Location, config: &ModuleConfig) {
+        let kind = module_kind(name);
+        if module_schema(kind).is_none() {
+            self.report_at(location, Severity::Error, format!("unknown module `{kind}`"), did_you_mean(kind, KINDS));
+            return;
+        }
+        let common = match config.get::<CommonConfig>(name) {
+            Ok(common) => common,
+            Err(e) => {
+                self.report_at(location, Severity::Error, e.to_string(), None);
+                return;
+            }
+        };
+        // a missing device is expected when the module hides itself then
+        let check_device = |kind, dir, device: &str| {
+            if common.hide_when.missing { None } else { check_device(kind, dir, device) }
+        };
+
+        let result = match kind {
+            "battery" => config.get::<BatteryConfig>(name).map(|c| check_device("battery", "/sys/class/power_supply", &c.name)),
+            "brightnessctl" => config.get::<BrightnessctlConfig>(name).map(|c| check_device("backlight", "/sys/class/backlight", &c.device_name)),
+            "filesystem" => config.get::<FilesystemConfig>(name).map(|c| check_mountpoint(&c.mountpoint)),
+            "network" => config.get::<NetworkConfig>(name).map(|c| check_device("network interface", "/sys/class/net", &c.interface)),
+            _ => build_check(kind, name, config).map(|()| None),
+        };
+
+        match result {
+            Ok(Some((message, help))) => {
+                self.report_at(location, Severity::Error, format!("{name}: {message}"), help);
+            }
+            Ok(None) => {}
+            Err(e) => self.report_at(location, Severity::Error, e.to_string(), None),
+        }
+    }
+
+    fn check_module_table(&mut self, name: &str, span: Range<usize>, table: &DeValue) {
+        let kind = module_kind(name);
+        let group = self.findings.groups.contains(name).then_some((&[][..], None));
+        let Some((fields, placeholders)) = module_schema(kind).or(group) else {
+            self.error(span, format!("unknown module `{kind}`"), did_you_mean(kind, KINDS));
+            return;
+        };
+        let Some(table) = table.as_table() else {
+            self.error(span, format!("module `{name}` must be a table"), None);
+            return;
+        };
+
+        let common_fields = struct_fields::<CommonConfig>();
+        let known: Vec<&str> = fields.iter().chain(common_fields).copied().collect();
+        for (key, value) in table.iter() {
+            let key_name: &str = key.get_ref();
+            if let Some((_, new)) = DEPRECATED_KEYS.iter().find(|(old, _)| *old == key_name) {
+                self.report(
+                    Severity::Warning,
+                    key.span(),
+                    format!("`{key_name}` is deprecated"),
+                    Some(format!("use `{new}` instead")),
+                );
+            } else if kind != "external" && !is_known_key(key_name, &known) {
+                self.error(
+                    key.span(),
+                    format!("unknown key `{key_name}` in module `{name}`"),
+                    did_you_mean(key_name, &known),
+                );
+                continue;
+            }
+
+            let DeValue::String(text) = value.get_ref() else {
+                continue;
+            };
+            if is_color_key(key_name) {
+                self.check_color(text, value.span());
+            } else if key_name == "error_format" {
+                self.check_placeholders(text, ERROR_PLACEHOLDERS, value.span());
+            } else if key_name.starts_with("format")
+                && let Some(placeholders) = placeholders
+            {
+                self.check_placeholders(text, placeholders, value.span());
+            }
+        }
+    }
+
+    /// Remember where a module of a section is first named, to report
+    /// problems with its configuration there
+    fn record_module(&mut self, name: &toml::Spanned<DeValue>) {
+        if let DeValue::String(module) = name.get_ref()
+            && !self.findings.module_locations.contains_key(module.as_ref())
+        {
+            let location = self.locate(name.span());
+            self.findings.module_locations.insert(module.to_string(), location);
+        }
+    }
+
+    fn check_group(&mut self, group: &DeTable) {
+        let fields = struct_fields::<GroupConfig>();
+        for (key, value) in group.iter() {
+            let key_name: &str = key.get_ref();
+            if !fields.contains(&key_name) {
+                self.error(key.span(), format!("unknown key `{key_name}` in group"), did_you_mean(key_name, fields));
+                continue;
+            }
+            if let ("modules", DeValue::Array(names)) = (key_name, value.get_ref()) {
+                for name in names.iter() {
+                    self.record_module(name);
+                }
+            }
+        }
+    }
+
+    fn check_sections(&mut self, sections: &DeTable) {
+        let fields = struct_fields::<SectionConfig>();
+        for (name, section) in sections.iter() {
+            let section_name: &str = name.get_ref();
+            if !SECTIONS.contains(&section_name) {
+                self.error(name.span(), format!("unknown section `{section_name}`"), did_you_mean(section_name, SECTIONS));
+                continue;
+            }
+            let Some(table) = section.get_ref().as_table() else {
+                self.error(name.span(), format!("section `{section_name}` must be a table"), None);
+                continue;
+            };
+
+            for (key, value) in table.iter() {
+                let key_name: &str = key.get_ref();
+                if !fields.contains(&key_name) {
+                    self.error(key.span(), format!("unknown key `{key_name}` in section"), did_you_mean(key_name, fields));
+                    continue;
+                }
+                if let ("powerline", DeValue::String(direction)) = (key_name, value.get_ref())
+                    && !POWERLINE_DIRECTIONS.contains(&direction.as_ref())
+                {
+                    self.error(
+                        value.span(),
+                        format!("unknown powerline direction `{direction}`"),
+                        Some("powerline separators point `left` or `right`".to_string()),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Check the keys of `[defaults]`, which may be any key of any
+    /// module
+    fn check_defaults(&mut self, defaults: &DeTable) {
+        let mut known: Vec<&str> = struct_fields::<CommonConfig>().to_vec();
+        for kind in KINDS {
+            if let Some((fields, _)) = module_schema(kind) {
+                known.extend(fields);
+            }
+        }
+        known.sort_unstable();
+        known.dedup();
+
+        for (key, value) in defaults.iter() {
+            let key_name: &str = key.get_ref();
+            if !is_known_key(key_name, &known) {
+                self.error(key.span(), format!("unknown key `{key_name}` in defaults"), did_you_mean(key_name, &known));
+                continue;
+            }
+            match value.get_ref() {
+                DeValue::String(text) if is_color_key(key_name) => self.check_color(text, value.span()),
+                DeValue::String(text) if key_name == "error_format" => {
+                    self.check_placeholders(text, ERROR_PLACEHOLDERS, value.span());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check_color(&mut self, color: &str, span: Range<usize>) {
+        if is_color(color) || is_interpolated(color) || self.findings.palette.contains(color) {
+            return;
+        }
+        let palette: Vec<&str> = self.findings.palette.iter().map(String::as_str).collect();
+        let help = did_you_mean(color, &palette)
+            .unwrap_or_else(|| "colors are written #RGB, #RRGGBB or #AARRGGBB, or are names from [colors]".to_string());
+        self.error(span, format!("invalid color `{color}`"), Some(help));
+    }
+
+    fn check_placeholders(&mut self, format: &str, placeholders: &[&str], span: Range<usize>) {
+        if is_interpolated(format) {
+            return;
+        }
+        for placeholder in format_placeholders(format) {
+            if !placeholders.contains(&placeholder) {
+                let help = did_you_mean(placeholder, placeholders).or_else(|| {
+                    let names: Vec<String> = placeholders.iter().map(|p| format!("{{{p}}}")).collect();
+                    Some(if names.is_empty() {
+                        "this format takes no placeholders".to_string()
+                    } else {
+                        format!("available placeholders: {}", names.join(", "))
+                    })
+                });
+                self.error(span.clone(), format!("unknown placeholder `{{{placeholder}}}`"), help);
+            }
+        }
+    }
+}
+
+/// Deserialize the config of modules that are not checked any further
+fn build_check(kind: &str, name: &str, config: &ModuleConfig) -> anyhow::Result<()> {
+    match kind {
+        "command" => config.get::<CommandConfig>(name).map(drop),
+        "cpu" => config.get::<CpuConfig>(name).map(drop),
+        "cputemp" => config.get::<CputempConfig>(name).map(drop),
+        "date" => config.get::<DateConfig>(name).map(drop),
+        "external" => config.get::<ExternalConfig>(name).map(drop),
+        "ipc" => config.get::<IpcConfig>(name).map(drop),
+        "kernel" => config.get::<KernelConfig>(name).map(drop),
+        "loadavg" => config.get::<LoadavgConfig>(name).map(drop),
+        "memory" => config.get::<MemoryConfig>(name).map(drop),
+        "stream" => config.get::<StreamConfig>(name).map(drop),
+        "uptime" => config.get::<UptimeConfig>(name).map(drop),
+        "wpctl" => config.get::<WpctlConfig>(name).map(drop),
+        "xkeyboard" => config.get::<XkeyboardConfig>(name).map(drop),
+        "xwindow" => config.get::<XwindowConfig>(name).map(drop),
+        "xworkspaces" => config.get::<XworkspacesConfig>(name).map(drop),
+        _ => Ok(()),
+    }
+}
+
+type Problem = Option<(String, Option<String>)>;
+
+/// Check that `name` is an entry of `dir`, e.g. a battery in
+/// `/sys/class/power_supply`
+fn check_device(what: &str, dir: &str, name: &str) -> Problem {
+    if Path::new(dir).join(name).exists() {
+        return None;
+    }
+    let available: Vec<String> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    let available: Vec<&str> = available.iter().map(String::as_str).collect();
+    Some((format!("{what} `{name}` does not exist"), did_you_mean(name, &available)))
+}
+
+fn check_mountpoint(mountpoint: &str) -> Problem {
+    let mounts = fs::read_to_string("/proc/mounts").ok()?;
+    let mountpoints: Vec<&str> = mounts.lines().filter_map(|line| line.split_whitespace().nth(1)).collect();
+    if mountpoints.contains(&mountpoint) {
+        return None;
+    }
+    Some((format!("`{mountpoint}` is not a mountpoint"), did_you_mean(mountpoint, &mountpoints)))
+}
+
+/// Whether a value is only known once variables and commands were
+/// substituted
+fn is_interpolated(value: &str) -> bool {
+    value.contains("${") || value.contains("$(")
+}
+
+/// Whether a key is one of `known`, or the `format_alt*` or
+/// `format_short*` version of one
+fn is_known_key(key: &str, known: &[&str]) -> bool {
+    known.contains(&key)
+        || FORMAT_VARIANTS
+            .iter()
+            .filter_map(|prefix| variant_key(key, prefix))
+            .any(|key| known.contains(&key.as_str()))
+}
+
+fn is_color_key(key: &str) -> bool {
+    key == "color" || key.starts_with("color_") || key.ends_with("_color")
+}
+
+/// An X resource reference like `xrdb:color5`
+fn is_resource(color: &str) -> bool {
+    color.strip_prefix("xrdb:").is_some_and(|name| !name.is_empty())
+}
+
+fn is_color(color: &str) -> bool {
+    color
+        .strip_prefix('#')
+        .is_some_and(|hex| matches!(hex.len(), 3 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Names between braces in a format, e.g. `volume` in `{volume}%`
+fn format_placeholders(format: &str) -> Vec<&str> {
+    let mut placeholders = Vec::new();
+    let mut rest = format;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        placeholders.push(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    placeholders
+}
+
+/// Suggest the candidate closest to a misspelled name
+fn did_you_mean(name: &str, candidates: &[&str]) -> Option<String> {
+    candidates
+        .iter()
+        .map(|candidate| (strsim::levenshtein(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= (name.chars().count() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| format!("did you mean `{candidate}`?"))
+}
+
+/// Field names of a config struct, including aliases
+///
+/// Derived `Deserialize` impls pass their field names to
+/// `deserialize_struct`, which this deserializer captures.
+fn struct_fields<T: DeserializeOwned>() -> &'static [&'static str] {
+    let mut fields: &'static [&'static str] = &[];
+    let _ = T::deserialize(FieldNames(&mut fields));
+    fields
+}
+
+struct FieldNames<'a>(&'a mut &'static [&'static str]);
+
+impl<'de> Deserializer<'de> for FieldNames<'_> {
+    type Error = de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("expected a struct"))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        *self.0 = fields;
+        Err(de::Error::custom("fields collected"))
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+        ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suggests_close_names() {
+        assert_eq!(did_you_mean("memry", KINDS).as_deref(), Some("did you mean `memory`?"));
+        assert_eq!(did_you_mean("intervl", &["interval", "icon"]).as_deref(), Some("did you mean `interval`?"));
+        assert_eq!(did_you_mean("weather", KINDS), None);
+        assert_eq!(did_you_mean("cpu", &[]), None);
+    }
+
+    #[test]
+    fn finds_placeholders() {
+        assert_eq!(format_placeholders("{hours}h {minutes}m"), ["hours", "minutes"]);
+        assert_eq!(format_placeholders("{}%{%}"), ["", "%"]);
+        assert_eq!(format_placeholders("no {placeholder"), Vec::<&str>::new());
+        assert!(format_placeholders("plain").is_empty());
+    }
+
+    #[test]
+    fn reports_unknown_placeholders() {
+        let mut findings = Findings::default();
+        let mut checker = Checker {
+            path: Path::new("config.toml"),
+            source: "format = \"{minuts} {x}\"",
+            findings: &mut findings,
+        };
+        checker.check_placeholders("{minuts} {x}", UPTIME_PLACEHOLDERS, 9..23);
+        checker.check_placeholders("{x}", &[], 9..23);
+        checker.check_placeholders("${FORMAT}", &[], 9..23);
+
+        let diagnostics: Vec<_> = findings.diagnostics.iter().map(|d| (d.message.as_str(), d.help.as_deref())).collect();
+        assert_eq!(
+            diagnostics,
+            [
+                ("unknown placeholder `{minuts}`", Some("did you mean `minutes`?")),
+                (
+                    "unknown placeholder `{x}`",
+                    Some(
+                        "available placeholders: {days}, {hours}, {minutes}, {seconds}, {total_days}, \
+                         {total_hours}, {total_minutes}, {total_seconds}"
+                    ),
+                ),
+                ("unknown placeholder `{x}`", Some("this format takes no placeholders")),
+            ]
+        );
+        assert!(findings.diagnostics.iter().all(|d| (d.line, d.column) == (1, 10)));
+    }
+
+    #[test]
+    fn recognizes_colors() {
+        assert!(is_color("#fff"));
+        assert!(is_color("#88c0d0"));
+        assert!(is_color("#ff88C0D0"));
+        assert!(!is_color("#12345"));
+        assert!(!is_color("#ggg"));
+        assert!(!is_color("88c0d0"));
+        assert!(!is_color("red"));
+    }
+
+    #[test]
+    fn collects_struct_fields() {
+        assert_eq!(struct_fields::<DateConfig>(), ["interval", "icon", "icon_color", "format"]);
+        // aliases are accepted as keys too
+        let fields = struct_fields::<XworkspacesConfig>();
+        assert!(fields.contains(&"separator") && fields.contains(&"sepparator"));
+        assert!(struct_fields::<String>().is_empty());
+    }
+
+    #[test]
+    fn checks_a_config_file() {
+        let path = std::env::temp_dir().join(format!("barrs-check-{}.toml", std::process::id()));
+        fs::write(
+            &path,
+            "frontend = \"lemonbar\"\n\
+             separator = \" | \"\n\
+             left = [\"date\", \"memry\"]\n\
+             \n\
+             [modules.date]\n\
+             interval = 1\n\
+             format = \"%H\"\n\
+             intervl = 5\n",
+        )
+        .unwrap();
+        let diagnostics = Config::check(&path, None);
+        fs::remove_file(&path).unwrap();
+
+        let diagnostics: Vec<String> = diagnostics.iter().map(ToString::to_string).collect();
+        let path = path.display();
+        assert_eq!(
+            diagnostics,
+            [
+                format!("{path}:3:17: error: unknown module `memry`\n  help: did you mean `memory`?"),
+                format!("{path}:8:1: error: unknown key `intervl` in module `date`\n  help: did you mean `interval`?"),
+            ]
+        );
+    }
+}
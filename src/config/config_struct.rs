@@ -1,32 +1,277 @@
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
 
 #[derive(Deserialize)]
 pub struct Config {
-    pub left: Vec<String>,
-    pub center: Vec<String>,
-    pub right: Vec<String>,
+    #[serde(default)]
+    pub left: Vec<SectionItem>,
+    #[serde(default)]
+    pub center: Vec<SectionItem>,
+    #[serde(default)]
+    pub right: Vec<SectionItem>,
+    /// Where the bar of `left`, `center` and `right` is written
+    #[serde(default)]
+    pub output: OutputConfig,
+    /// More bars, sharing the modules of this one
+    #[serde(default)]
+    pub bars: BTreeMap<String, BarConfig>,
+    /// Draw the bar once on every RandR monitor, in lemonbar's
+    /// `%{Sn}` blocks
+    #[serde(default)]
+    pub per_monitor: bool,
     pub separator: String,
+    /// Widest the bar may get, in columns
+    pub width: Option<usize>,
+    /// Per-section separators, caps and powerline coloring
+    #[serde(default)]
+    pub sections: SectionsConfig,
     pub frontend: String,
+    #[serde(default = "default_instance")]
+    pub instance: String,
+    #[serde(default = "default_error_format")]
+    pub error_format: String,
+    pub error_color: Option<String>,
+    /// Allow `$(cmd)` in config values
+    #[serde(default)]
+    pub command_substitution: bool,
+    /// Keys every module inherits unless it sets them itself
+    #[serde(default)]
+    pub defaults: toml::Table,
+    /// Color names usable wherever a color is expected
+    #[serde(default)]
+    pub colors: HashMap<String, String>,
     pub modules: ModuleConfig,
 }
 
-#[derive(Deserialize)]
+impl Config {
+    /// Every bar to draw, by name, the unnamed one being made of the
+    /// top-level sections
+    ///
+    /// The top-level sections are left out when they are empty and
+    /// named bars are defined.
+    pub fn layouts(&self) -> Vec<(Option<&str>, BarConfig)> {
+        let mut layouts = Vec::new();
+        if self.bars.is_empty() || !(self.left.is_empty() && self.center.is_empty() && self.right.is_empty()) {
+            let main = BarConfig {
+                left: self.left.clone(),
+                center: self.center.clone(),
+                right: self.right.clone(),
+                output: self.output.clone(),
+                ..Default::default()
+            };
+            layouts.push((None, main));
+        }
+        layouts.extend(self.bars.iter().map(|(name, bar)| (Some(name.as_str()), bar.clone())));
+        layouts
+    }
+}
+
+fn default_instance() -> String {
+    crate::ipc::DEFAULT_INSTANCE.to_string()
+}
+
+fn default_error_format() -> String {
+    "error: {error}".to_string()
+}
+
+/// A bar of its own, e.g. `[bars.bottom]`, falling back to the
+/// top-level keys for anything it does not set
+#[derive(Deserialize, Default, Clone)]
+pub struct BarConfig {
+    #[serde(default)]
+    pub left: Vec<SectionItem>,
+    #[serde(default)]
+    pub center: Vec<SectionItem>,
+    #[serde(default)]
+    pub right: Vec<SectionItem>,
+    #[serde(default)]
+    pub output: OutputConfig,
+    pub separator: Option<String>,
+    pub sections: Option<SectionsConfig>,
+    pub frontend: Option<String>,
+    pub width: Option<usize>,
+    pub per_monitor: Option<bool>,
+}
+
+/// Where the frames of a bar are written
+#[derive(Deserialize, Default, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputConfig {
+    #[default]
+    Stdout,
+    /// A named pipe, created when missing, e.g.
+    /// `output = { pipe = "/tmp/barrs-top" }`
+    Pipe(PathBuf),
+    /// The standard input of a command run with `sh -c`, e.g.
+    /// `output = { command = "lemonbar -b | sh" }`
+    Command(String),
+}
+
+impl std::fmt::Display for OutputConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputConfig::Stdout => write!(f, "stdout"),
+            OutputConfig::Pipe(path) => write!(f, "pipe {}", path.display()),
+            OutputConfig::Command(command) => write!(f, "command `{command}`"),
+        }
+    }
+}
+
+/// An entry of `left`, `center` or `right`
+#[derive(Deserialize, Clone)]
+#[serde(untagged)]
+pub enum SectionItem {
+    /// A module, e.g. `"date"` or `"filesystem#home"`
+    Module(String),
+    Group(GroupConfig),
+}
+
+impl SectionItem {
+    /// Names of the modules of the entry
+    pub fn modules(&self) -> &[String] {
+        match self {
+            SectionItem::Module(name) => std::slice::from_ref(name),
+            SectionItem::Group(group) => &group.modules,
+        }
+    }
+}
+
+/// Modules drawn as one entry, collapsed to a summary until expanded
+#[derive(Deserialize, Clone)]
+pub struct GroupConfig {
+    /// Name to expand or collapse the group with, e.g. `barrs toggle system`
+    pub group: String,
+    pub modules: Vec<String>,
+    /// Replaces the section's separator between the group's modules
+    pub separator: Option<String>,
+    /// Shown while the group is collapsed
+    pub format: String,
+    /// Shown before the modules while the group is expanded, `format`
+    /// by default
+    pub format_expanded: Option<String>,
+    /// Start expanded
+    #[serde(default)]
+    pub expanded: bool,
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct SectionsConfig {
+    #[serde(default)]
+    pub left: SectionConfig,
+    #[serde(default)]
+    pub center: SectionConfig,
+    #[serde(default)]
+    pub right: SectionConfig,
+}
+
+/// How the modules of a section are joined
+#[derive(Deserialize, Default, Clone)]
+pub struct SectionConfig {
+    /// Replaces the global `separator` in this section
+    pub separator: Option<String>,
+    /// Color separators from the backgrounds of the modules around them
+    pub powerline: Option<Powerline>,
+    /// Drawn before the first module, in the color of its background
+    pub left_cap: Option<String>,
+    /// Drawn after the last module, in the color of its background
+    pub right_cap: Option<String>,
+}
+
+/// Which way powerline separators point, so the arrow takes the color of
+/// the module it points away from
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Powerline {
+    Left,
+    Right,
+}
+
+/// Options every module accepts on top of its own configuration
+#[derive(Deserialize, Default, Clone)]
+pub struct CommonConfig {
+    pub error_format: Option<String>,
+    pub error_color: Option<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Refresh the module when `SIGRTMIN+<signal>` is received
+    pub signal: Option<u8>,
+    #[serde(default)]
+    pub hide_when: HideWhen,
+    /// Color drawn behind the module and its padding
+    pub background_color: Option<String>,
+    /// Spaces on each side of the module
+    #[serde(default)]
+    pub padding: usize,
+    /// Longest value shown, in grapheme clusters
+    pub max_width: Option<usize>,
+    /// What to do with values longer than `max_width`
+    #[serde(default)]
+    pub overflow: Overflow,
+    /// Grapheme clusters scrolled per second with `overflow = "marquee"`
+    pub marquee_speed: Option<u32>,
+    /// Modules with the lowest priority are shortened or dropped first
+    /// when the bar is wider than `width`
+    #[serde(default)]
+    pub priority: i32,
+    /// Narrowest the value is shortened to before the module is dropped
+    pub min_width: Option<usize>,
+    /// Outputs the module is shown on when the bar is drawn per
+    /// monitor, e.g. `HDMI-1` or `primary`, all of them when empty
+    #[serde(default)]
+    pub monitors: Vec<String>,
+}
+
+/// How values longer than `max_width` are shortened
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Cut the value and end it with `…`
+    #[default]
+    Ellipsis,
+    /// Scroll the value through `max_width` columns
+    Marquee,
+}
+
+/// Conditions under which a module is left out of its section, any of
+/// which is enough to hide it
+#[derive(Deserialize, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct HideWhen {
+    /// The value is empty or only whitespace
+    #[serde(default)]
+    pub empty: bool,
+    /// The battery, interface or other device the module reads is missing
+    #[serde(default)]
+    pub missing: bool,
+    /// The value matches this regex
+    #[serde(default, deserialize_with = "deserialize_regex")]
+    pub matches: Option<Regex>,
+    /// The module reports this state, e.g. `discharging` or `down`
+    pub state: Option<String>,
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern)
+        .map(Some)
+        .map_err(|e| serde::de::Error::custom(format!("invalid regex in `hide_when.matches`: {e}")))
+}
+
+/// Configuration tables of all modules, keyed by module name
+///
+/// A module can be loaded several times by giving each instance a name,
+/// e.g. `filesystem#home`. An instance uses the `[modules.filesystem]`
+/// table with the keys of `[modules."filesystem#home"]` on top, and
+/// both inherit from `[defaults]`.
+#[derive(Deserialize, Default)]
+#[serde(transparent)]
 pub struct ModuleConfig {
-    pub battery: BatteryConfig,
-    pub brightnessctl: BrightnessctlConfig,
-    pub cpu: CpuConfig,
-    pub cputemp: CputempConfig,
-    pub date: DateConfig,
-    pub filesystem: FilesystemConfig,
-    pub kernel: KernelConfig,
-    pub loadavg: LoadavgConfig,
-    pub memory: MemoryConfig,
-    pub network: NetworkConfig,
-    pub uptime: UptimeConfig,
-    pub wpctl: WpctlConfig,
-    pub xkeyboard: XkeyboardConfig,
-    pub xwindow: XwindowConfig,
-    pub xworkspaces: XworkspacesConfig,
+    pub tables: HashMap<String, toml::Table>,
+    /// Copy of [`Config::defaults`], filled in by [`Config::load`]
+    #[serde(skip)]
+    pub defaults: toml::Table,
 }
 
 #[derive(Deserialize)]
@@ -49,6 +294,33 @@ pub struct BrightnessctlConfig {
     pub format: String,
 }
 
+#[derive(Deserialize)]
+pub struct CommandConfig {
+    pub exec: String,
+    #[serde(default = "default_shell")]
+    pub shell: String,
+    pub interval: Option<u64>,
+    pub signal_id: Option<u8>,
+    /// Seconds after which the command is killed
+    pub timeout: Option<u64>,
+    pub icon: Option<String>,
+    pub icon_color: Option<String>,
+    #[serde(default = "default_command_format")]
+    pub format: String,
+    pub color: Option<String>,
+    /// Format used instead of `format` when the command exits non-zero
+    pub format_failed: Option<String>,
+    pub color_failed: Option<String>,
+}
+
+fn default_shell() -> String {
+    "sh".to_string()
+}
+
+fn default_command_format() -> String {
+    "{stdout}".to_string()
+}
+
 #[derive(Deserialize)]
 pub struct CpuConfig {
     pub interval: u64,
@@ -73,6 +345,17 @@ pub struct DateConfig {
     pub format: String,
 }
 
+#[derive(Deserialize)]
+pub struct ExternalConfig {
+    pub exec: String,
+    #[serde(default = "default_shell")]
+    pub shell: String,
+    pub icon: Option<String>,
+    pub icon_color: Option<String>,
+    /// Format using the typed values sent by the module, `{text}` by default
+    pub format: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct FilesystemConfig {
     pub interval: u64,
@@ -82,6 +365,16 @@ pub struct FilesystemConfig {
     pub mountpoint: String,
 }
 
+#[derive(Deserialize)]
+pub struct IpcConfig {
+    pub icon: Option<String>,
+    pub icon_color: Option<String>,
+    #[serde(default)]
+    pub default: String,
+    pub color: Option<String>,
+    pub expire: Option<u64>,
+}
+
 #[derive(Deserialize)]
 pub struct KernelConfig {
     pub interval: u64,
@@ -115,6 +408,25 @@ pub struct NetworkConfig {
     pub format: String,
 }
 
+#[derive(Deserialize)]
+pub struct StreamConfig {
+    pub exec: String,
+    #[serde(default = "default_shell")]
+    pub shell: String,
+    pub icon: Option<String>,
+    pub icon_color: Option<String>,
+    #[serde(default = "default_stream_format")]
+    pub format: String,
+    pub color: Option<String>,
+    /// Parse every line as a JSON object whose fields become placeholders
+    #[serde(default)]
+    pub json: bool,
+}
+
+fn default_stream_format() -> String {
+    "{line}".to_string()
+}
+
 #[derive(Deserialize)]
 pub struct UptimeConfig {
     pub interval: u64,
@@ -142,7 +454,8 @@ pub struct XkeyboardConfig {
 pub struct XwindowConfig {
     pub icon: Option<String>,
     pub icon_color: Option<String>,
-    pub max_length: u32,
+    /// Deprecated, use `max_width`
+    pub max_length: Option<usize>,
     pub empty_name: String,
 }
 
@@ -154,5 +467,10 @@ pub struct XworkspacesConfig {
     pub format_empty: String,
     pub format_occupied: String,
     pub format_urgent: String,
-    pub sepparator: String,
+    #[serde(alias = "sepparator")]
+    pub separator: String,
+    /// Only show the workspaces of each monitor when the bar is drawn
+    /// per monitor, going by `_NET_DESKTOP_VIEWPORT`
+    #[serde(default)]
+    pub pin_workspaces: bool,
 }
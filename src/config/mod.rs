@@ -2,3 +2,7 @@ mod config_struct;
 pub use config_struct::*;
 
 mod parse;
+pub use parse::module_kind;
+
+mod check;
+pub use check::{Diagnostic, Severity};
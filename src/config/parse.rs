@@ -1,10 +1,14 @@
 use anyhow::{Context, Result};
+use serde::de::DeserializeOwned;
 use std::fs;
-use std::path::PathBuf;
-use super::Config;
+use std::path::{Path, PathBuf};
+use super::{Config, ModuleConfig};
 
 impl Config {
-    pub fn parse() -> Result<Self> {
+    /// Path of the config file, the first one found of `./config.toml`,
+    /// `$XDG_CONFIG_HOME/barrs/config.toml` and
+    /// `~/.config/barrs/config.toml`
+    pub fn path() -> Result<PathBuf> {
         let mut paths = Vec::new();
 
         paths.push(PathBuf::from("config.toml"));
@@ -17,20 +21,384 @@ impl Config {
             paths.push(home_dir.join(".config").join("barrs").join("config.toml"));
         }
 
-        for path in paths {
-            if path.exists() {
-                let contents = fs::read_to_string(&path)
-                    .with_context(|| format!("failed to read config file at {:?}", path))?;
-                let config: Config = toml::from_str(&contents)
-                    .map_err(|e| anyhow::anyhow!(
-                        "failed to parse config file at {:?}:\n{}",
-                        path,
-                        e,
-                    ))?;
-                return Ok(config);
+        paths
+            .into_iter()
+            .find(|path| path.exists())
+            .ok_or_else(|| anyhow::anyhow!("no config file found in any of the expected locations"))
+    }
+
+    /// Load the config file found by [`Config::path`], with the profile
+    /// named after the hostname applied
+    pub fn parse() -> Result<Self> {
+        Self::load(&Self::path()?, None)
+    }
+
+    /// Load a config file with its includes, applying `profile` or,
+    /// when none is given, the profile named after the hostname
+    pub fn load(path: &Path, profile: Option<&str>) -> Result<Self> {
+        let table = load_table(path, profile)?;
+        let mut config: Config = toml::Value::Table(table).try_into().map_err(|e: toml::de::Error| {
+            anyhow::anyhow!("invalid config file at {:?}: {}", path, e.message())
+        })?;
+        config.modules.defaults = config.defaults.clone();
+        Ok(config)
+    }
+}
+
+/// Read a config file into a table, with its includes merged below it
+/// and its profile merged on top
+pub(super) fn load_table(path: &Path, profile: Option<&str>) -> Result<toml::Table> {
+    let mut table = read_with_includes(path, &mut Vec::new())?;
+
+    let mut profiles = match table.remove("profile") {
+        Some(toml::Value::Table(profiles)) => profiles,
+        Some(_) => anyhow::bail!("`profile` must be a table of profiles"),
+        None => toml::Table::new(),
+    };
+    let overrides = match profile {
+        Some(profile) => Some(
+            profiles
+                .remove(profile)
+                .ok_or_else(|| anyhow::anyhow!("no profile named {profile} in {:?}", path))?,
+        ),
+        None => hostname().and_then(|hostname| profiles.remove(&hostname)),
+    };
+    match overrides {
+        Some(toml::Value::Table(overrides)) => merge(&mut table, overrides),
+        Some(_) => anyhow::bail!("profiles must be tables"),
+        None => {}
+    }
+
+    let commands = matches!(table.get("command_substitution"), Some(toml::Value::Boolean(true)));
+    for (_, value) in table.iter_mut() {
+        interpolate_value(value, commands)?;
+    }
+
+    Ok(table)
+}
+
+fn interpolate_value(value: &mut toml::Value, commands: bool) -> Result<()> {
+    match value {
+        toml::Value::String(s) => *s = interpolate(s, commands)?,
+        toml::Value::Array(values) => {
+            for value in values {
+                interpolate_value(value, commands)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            // commands are left to the shell, which has its own `$`
+            // syntax and reads the same environment
+            for (_, value) in table.iter_mut().filter(|(key, _)| key.as_str() != "exec") {
+                interpolate_value(value, commands)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace `${VAR}` and `${VAR:-default}` with environment variables
+/// and, when `commands` is set, `$(cmd)` with the output of `cmd`.
+/// `$$` is a literal `$`. Defaults can hold substitutions themselves,
+/// e.g. `${A:-${B}}`, which are only made when the default is used.
+/// `${...}` not starting with a variable name is kept as written.
+fn interpolate(s: &str, commands: bool) -> Result<String> {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(after) = rest.strip_prefix("$$") {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${")
+            && starts_with_variable(after)
+        {
+            let end = closing_brace(after).ok_or_else(|| anyhow::anyhow!("unclosed `${{` in {s:?}"))?;
+            let (name, default) = match after[..end].split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (&after[..end], None),
+            };
+            // like the shell, an empty variable also uses the default
+            match (std::env::var(name), default) {
+                (Ok(value), Some(default)) if value.is_empty() => result.push_str(&interpolate(default, commands)?),
+                (Ok(value), _) => result.push_str(&value),
+                (Err(_), Some(default)) => result.push_str(&interpolate(default, commands)?),
+                (Err(_), None) => anyhow::bail!("environment variable {name} is not set"),
+            }
+            rest = &after[end + 1..];
+        } else if commands && let Some(after) = rest.strip_prefix("$(") {
+            let end = closing_paren(after).ok_or_else(|| anyhow::anyhow!("unclosed `$(` in {s:?}"))?;
+            result.push_str(&run_substitution(&after[..end])?);
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+            rest = &rest[1..];
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Whether `s` starts with a variable name followed by `}`, `:-` or
+/// nothing, which is reported as unclosed
+fn starts_with_variable(s: &str) -> bool {
+    let len = s.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(s.len());
+    let (name, rest) = s.split_at(len);
+    !name.is_empty()
+        && !name.starts_with(|c: char| c.is_ascii_digit())
+        && (rest.is_empty() || rest.starts_with('}') || rest.starts_with(":-"))
+}
+
+/// Index of the brace closing an opened `${`, skipping the `${...}`
+/// nested in it
+fn closing_brace(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut chars = s.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '$' if chars.next_if(|&(_, next)| next == '{').is_some() => depth += 1,
+            '$' => {
+                chars.next_if(|&(_, next)| next == '$');
+            }
+            '}' if depth == 0 => return Some(i),
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Index of the parenthesis closing an opened one, allowing nesting
+/// and skipping parentheses that the shell reads as quoted or escaped
+fn closing_paren(s: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut chars = s.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') => quote = None,
+            (Some('\''), _) => {}
+            (_, '\\') => {
+                chars.next();
             }
+            (Some('"'), '"') => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => return Some(i),
+            (None, ')') => depth -= 1,
+            _ => {}
         }
+    }
+    None
+}
+
+fn run_substitution(command: &str) -> Result<String> {
+    let output = std::process::Command::new("sh")
+        .args(["-c", command])
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .with_context(|| format!("failed to run $({command})"))?;
+    if !output.status.success() {
+        anyhow::bail!("$({command}) failed ({})", output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim_end_matches('\n').to_string())
+}
+
+/// Read a config file and the files it includes, which are resolved
+/// relative to it. `stack` holds the files being read, to catch cycles.
+fn read_with_includes(path: &Path, stack: &mut Vec<PathBuf>) -> Result<toml::Table> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("failed to read config file at {:?}", path))?;
+    if stack.contains(&canonical) {
+        anyhow::bail!("{:?} includes itself", path);
+    }
+
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read config file at {:?}", path))?;
+    let mut table: toml::Table = toml::from_str(&contents)
+        .map_err(|e| anyhow::anyhow!(
+            "failed to parse config file at {:?}:\n{}",
+            path,
+            e,
+        ))?;
+
+    let includes: Vec<String> = match table.remove("include") {
+        Some(includes) => includes
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("`include` in {:?} must be a list of paths", path))?,
+        None => Vec::new(),
+    };
+
+    stack.push(canonical);
+    let mut merged = toml::Table::new();
+    for include in includes {
+        let include = path.parent().unwrap_or(Path::new("")).join(include);
+        merge(&mut merged, read_with_includes(&include, stack)?);
+    }
+    stack.pop();
+
+    merge(&mut merged, table);
+    Ok(merged)
+}
+
+/// Merge `overrides` into `base`, recursing into tables present in
+/// both. Any other value, including section lists, is replaced.
+fn merge(base: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => merge(base, overrides),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn hostname() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|hostname| hostname.trim().to_string())
+}
+
+/// Module kind of an instance name, e.g. `filesystem` for `filesystem#home`
+pub fn module_kind(name: &str) -> &str {
+    name.split_once('#').map_or(name, |(kind, _)| kind)
+}
+
+/// Prefixes of the other versions of a module's formats, the alternate
+/// ones toggled by clicks and the short ones used when space runs out
+pub(super) const FORMAT_VARIANTS: &[&str] = &["format_alt", "format_short"];
+
+/// Key a variant key stands in for, e.g. `format_full` for
+/// `format_alt_full` with the `format_alt` prefix
+pub(super) fn variant_key(key: &str, prefix: &str) -> Option<String> {
+    let suffix = key.strip_prefix(prefix)?;
+    (suffix.is_empty() || suffix.starts_with('_')).then(|| format!("format{suffix}"))
+}
+
+impl ModuleConfig {
+    /// Configuration table of a module instance, with the instance
+    /// table merged on top of the table of its kind and the defaults
+    pub fn table(&self, name: &str) -> toml::Table {
+        let mut table = self.defaults.clone();
+        if let Some(kind) = self.tables.get(module_kind(name)) {
+            table.extend(kind.clone());
+        }
+        if let Some(instance) = self.tables.get(name) {
+            table.extend(instance.clone());
+        }
+        table
+    }
+
+    /// Configuration where the keys of a module instance starting with
+    /// `prefix` replace its `format*` keys, e.g. `format_alt_full`
+    /// replaces `format_full`, or `None` when it has no such key
+    pub fn variant(&self, name: &str, prefix: &str) -> Option<ModuleConfig> {
+        let mut table = self.table(name);
+        let variants: Vec<(String, toml::Value)> = table
+            .iter()
+            .filter_map(|(key, value)| Some((variant_key(key, prefix)?, value.clone())))
+            .collect();
+        if variants.is_empty() {
+            return None;
+        }
+
+        table.extend(variants);
+        let mut config = ModuleConfig {
+            tables: self.tables.clone(),
+            defaults: self.defaults.clone(),
+        };
+        config.tables.insert(name.to_string(), table);
+        Some(config)
+    }
+
+    /// Deserialize the configuration of a module instance
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        toml::Value::Table(self.table(name))
+            .try_into()
+            .map_err(|e| anyhow::anyhow!("invalid configuration for module {name}: {}", e.message()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(toml: &str) -> toml::Table {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn interpolate_variables() {
+        let name = env!("CARGO_PKG_NAME");
+        assert_eq!(interpolate("${CARGO_PKG_NAME}", false).unwrap(), name);
+        assert_eq!(interpolate("a ${BARRS_TEST_UNSET:-b} c", false).unwrap(), "a b c");
+        assert_eq!(interpolate("${BARRS_TEST_UNSET:-}", false).unwrap(), "");
+        assert_eq!(interpolate("$$HOME $5", false).unwrap(), "$HOME $5");
+        assert!(interpolate("${BARRS_TEST_UNSET}", false).is_err());
+        assert!(interpolate("${BARRS_TEST_UNSET", false).is_err());
+    }
+
+    #[test]
+    fn interpolate_keeps_shell_syntax() {
+        assert_eq!(interpolate("${f##*/} ${#f} ${1} ${}", false).unwrap(), "${f##*/} ${#f} ${1} ${}");
+        assert_eq!(interpolate("${f:0:2}", false).unwrap(), "${f:0:2}");
+    }
+
+    #[test]
+    fn exec_is_not_interpolated() {
+        let mut value = toml::Value::Table(table(
+            "[modules.command]\nexec = \"echo $$ ${BARRS_TEST_UNSET}\"\nformat = \"$${stdout}\"",
+        ));
+        interpolate_value(&mut value, false).unwrap();
+        let command = &value["modules"]["command"];
+        assert_eq!(command["exec"].as_str(), Some("echo $$ ${BARRS_TEST_UNSET}"));
+        assert_eq!(command["format"].as_str(), Some("${stdout}"));
+    }
+
+    #[test]
+    fn interpolate_nested_defaults() {
+        let name = env!("CARGO_PKG_NAME");
+        assert_eq!(interpolate("${BARRS_TEST_UNSET:-${CARGO_PKG_NAME}}", false).unwrap(), name);
+        assert_eq!(
+            interpolate("${BARRS_TEST_UNSET:-${BARRS_TEST_UNSET:-x}}y", false).unwrap(),
+            "xy",
+        );
+        // defaults that are not used are not substituted
+        assert_eq!(interpolate("${CARGO_PKG_NAME:-${BARRS_TEST_UNSET}}", false).unwrap(), name);
+    }
+
+    #[test]
+    fn interpolate_commands() {
+        assert_eq!(interpolate("$(echo a)b", true).unwrap(), "ab");
+        assert_eq!(interpolate("$(echo $(echo a))", true).unwrap(), "a");
+        assert_eq!(interpolate("$(printf ')')", true).unwrap(), ")");
+        assert_eq!(interpolate("$(printf \"(\")", true).unwrap(), "(");
+        assert_eq!(interpolate("$(printf \\))", true).unwrap(), ")");
+        assert_eq!(interpolate("$(echo a)", false).unwrap(), "$(echo a)");
+        assert!(interpolate("$(echo a", true).is_err());
+    }
+
+    #[test]
+    fn merge_replaces_arrays() {
+        let mut base = table("left = [\"a\", \"b\"]\n[modules.date]\nformat = \"x\"\ninterval = 1");
+        merge(&mut base, table("left = [\"c\"]\n[modules.date]\nformat = \"y\""));
+        assert_eq!(
+            base,
+            table("left = [\"c\"]\n[modules.date]\nformat = \"y\"\ninterval = 1"),
+        );
+    }
 
-        anyhow::bail!("no config file found in any of the expected locations");
+    #[test]
+    fn variant_keys() {
+        assert_eq!(variant_key("format_alt", "format_alt").as_deref(), Some("format"));
+        assert_eq!(variant_key("format_alt_full", "format_alt").as_deref(), Some("format_full"));
+        assert_eq!(variant_key("format_short_muted", "format_short").as_deref(), Some("format_muted"));
+        assert_eq!(variant_key("format_alternate", "format_alt"), None);
+        assert_eq!(variant_key("format_full", "format_alt"), None);
     }
 }
@@ -0,0 +1,25 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::UnixStream;
+use super::{Request, Response};
+
+/// Send a single request to the instance listening on `socket_path` and
+/// wait for its reply
+pub async fn send(socket_path: &Path, request: &Request) -> Result<Response> {
+    let mut stream = UnixStream::connect(socket_path)
+        .await
+        .with_context(|| format!("could not connect to {:?}", socket_path))?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes()).await?;
+
+    let mut reply = String::new();
+    BufReader::new(stream).read_line(&mut reply).await?;
+    if reply.is_empty() {
+        anyhow::bail!("connection closed without a reply");
+    }
+
+    serde_json::from_str(&reply).context("invalid reply")
+}
@@ -0,0 +1,11 @@
+mod client;
+pub use client::send;
+
+mod protocol;
+pub use protocol::{MAX_REQUEST_SIZE, Request, Response};
+
+mod server;
+pub use server::{Command, Server};
+
+mod socket;
+pub use socket::{DEFAULT_INSTANCE, instance_sockets, prepare_socket_dir, socket_path};
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Largest request the server accepts, in bytes
+pub const MAX_REQUEST_SIZE: u64 = 64 * 1024;
+
+/// A request sent to a running instance
+///
+/// Requests and responses are single JSON objects terminated by a
+/// newline, e.g. `{"command":"update","target":"1"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "lowercase")]
+pub enum Request {
+    /// Refresh the modules matching `target`
+    Update { target: String },
+    /// List the loaded modules
+    List,
+    /// Get the current output of a module
+    Get { target: String },
+    /// Re-read the config file and rebuild all modules
+    Reload,
+    /// Stop rendering a module
+    Hide { target: String },
+    /// Render a previously hidden module again
+    Show { target: String },
+    /// Set the text of ipc modules, optionally clearing it after
+    /// `expire` seconds
+    Set {
+        target: String,
+        text: String,
+        #[serde(default)]
+        color: Option<String>,
+        #[serde(default)]
+        expire: Option<u64>,
+    },
+    /// Switch the modules matching `target` to their other format
+    Toggle { target: String },
+    /// Show the modules of the group named `target`
+    Expand { target: String },
+    /// Show only the summary of the group named `target`
+    Collapse { target: String },
+    /// Send a mouse button press to the modules matching `target`
+    Click { target: String, button: u8 },
+    /// Shut the instance down
+    Quit,
+}
+
+/// The reply to a [`Request`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum Response {
+    Ok {
+        #[serde(default, skip_serializing_if = "Value::is_null")]
+        data: Value,
+    },
+    Error { message: String },
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Response::Ok { data: Value::Null }
+    }
+
+    pub fn data(data: impl Serialize) -> Self {
+        match serde_json::to_value(data) {
+            Ok(data) => Response::Ok { data },
+            Err(e) => Response::error(format!("failed to serialize reply: {e}")),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Response::Error { message: message.into() }
+    }
+}
+
+impl From<anyhow::Result<()>> for Response {
+    fn from(result: anyhow::Result<()>) -> Self {
+        match result {
+            Ok(()) => Response::ok(),
+            Err(e) => Response::error(format!("{e:#}")),
+        }
+    }
+}
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use std::fs::{self, Permissions};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+use tokio::time::{Duration, sleep, timeout};
+use super::{MAX_REQUEST_SIZE, Request, Response};
+
+/// A request received by the server, along with the channel its reply
+/// should be sent on
+pub type Command = (Request, oneshot::Sender<Response>);
+
+/// How long a client may take to send a request before it is dropped
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Listens on the instance socket and forwards requests to the main loop
+pub struct Server {
+    path: PathBuf,
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl Server {
+    /// Bind the socket at `path` and start accepting connections
+    ///
+    /// Fails if another instance is already listening on the socket. A
+    /// stale socket left behind by a crashed instance is replaced.
+    pub fn bind(path: &Path) -> Result<(Self, mpsc::Receiver<Command>)> {
+        if path.exists() {
+            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+                anyhow::bail!("another instance is already listening on {:?}", path);
+            }
+            fs::remove_file(path)
+                .with_context(|| format!("failed to remove stale socket {:?}", path))?;
+        }
+        let listener = UnixListener::bind(path)
+            .with_context(|| format!("failed to bind socket {:?}", path))?;
+        fs::set_permissions(path, Permissions::from_mode(0o600))
+            .with_context(|| format!("failed to set permissions on {:?}", path))?;
+
+        let (requests_tx, requests_rx) = mpsc::channel(16);
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let task = tokio::spawn(serve(listener, requests_tx, shutdown_rx));
+
+        Ok((
+            Self {
+                path: path.to_path_buf(),
+                shutdown: shutdown_tx,
+                task,
+            },
+            requests_rx,
+        ))
+    }
+
+    /// Stop accepting connections, give pending replies a moment to be
+    /// written and remove the socket file
+    ///
+    /// The request receiver should be dropped first, so connections
+    /// still waiting on the main loop are answered with an error.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.task.await;
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+async fn serve(
+    listener: UnixListener,
+    requests: mpsc::Sender<Command>,
+    mut shutdown: oneshot::Receiver<()>,
+) {
+    let mut connections = JoinSet::new();
+
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    connections.spawn(handle_connection(stream, requests.clone()));
+                }
+                Err(e) => {
+                    eprintln!("warning: failed to accept connection: {e}");
+                    sleep(Duration::from_millis(100)).await;
+                }
+            },
+            Some(_) = connections.join_next(), if !connections.is_empty() => {}
+        }
+    }
+
+    let _ = timeout(Duration::from_secs(1), async {
+        while connections.join_next().await.is_some() {}
+    }).await;
+}
+
+/// Answer every request sent on a connection until the client closes it
+async fn handle_connection(stream: UnixStream, requests: mpsc::Sender<Command>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        let mut line = String::new();
+        let read = timeout(
+            READ_TIMEOUT,
+            (&mut reader).take(MAX_REQUEST_SIZE).read_line(&mut line),
+        ).await;
+
+        let (response, done) = match read {
+            Err(_) | Ok(Ok(0)) => return,
+            Ok(Err(e)) => (Response::error(format!("failed to read request: {e}")), true),
+            Ok(Ok(n)) if n as u64 >= MAX_REQUEST_SIZE && !line.ends_with('\n') => {
+                (Response::error("request too large"), true)
+            }
+            Ok(Ok(_)) => match serde_json::from_str::<Request>(&line) {
+                Ok(request) => (dispatch(request, &requests).await, false),
+                Err(e) => (Response::error(format!("invalid request: {e}")), false),
+            },
+        };
+
+        let mut reply = match serde_json::to_string(&response) {
+            Ok(reply) => reply,
+            Err(_) => return,
+        };
+        reply.push('\n');
+        if writer.write_all(reply.as_bytes()).await.is_err() || done {
+            return;
+        }
+    }
+}
+
+async fn dispatch(request: Request, requests: &mpsc::Sender<Command>) -> Response {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    if requests.send((request, reply_tx)).await.is_err() {
+        return Response::error("instance is shutting down");
+    }
+    reply_rx.await.unwrap_or_else(|_| Response::error("instance is shutting down"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Send `lines` on a single connection, answering every request
+    /// that reaches the main loop with `ok`, and return the replies
+    async fn exchange(lines: &[&str]) -> Vec<String> {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (requests, mut rx) = mpsc::channel::<Command>(1);
+        tokio::spawn(handle_connection(server, requests));
+        tokio::spawn(async move {
+            while let Some((_, reply)) = rx.recv().await {
+                let _ = reply.send(Response::ok());
+            }
+        });
+
+        let (reader, mut writer) = client.into_split();
+        let mut reader = BufReader::new(reader);
+        let mut replies = Vec::new();
+        for line in lines {
+            writer.write_all(format!("{line}\n").as_bytes()).await.unwrap();
+            let mut reply = String::new();
+            reader.read_line(&mut reply).await.unwrap();
+            replies.push(reply.trim_end().to_string());
+        }
+        replies
+    }
+
+    #[tokio::test]
+    async fn answers_bad_requests_and_keeps_the_connection() {
+        let replies = exchange(&[
+            "not json",
+            r#"{"command":"restart"}"#,
+            r#"{"command":"update"}"#,
+            r#"{"command":"click","target":"date","button":"left"}"#,
+            r#"{"target":"date"}"#,
+            r#"{"command":"update","target":"1"}"#,
+        ])
+        .await;
+
+        assert_eq!(replies.len(), 6);
+        for (reply, expected) in replies[..5].iter().zip([
+            "expected ident",
+            "unknown variant `restart`",
+            "missing field `target`",
+            "invalid type: string \\\"left\\\"",
+            "missing field `command`",
+        ]) {
+            assert!(reply.starts_with(r#"{"status":"error","message":"invalid request: "#), "{reply}");
+            assert!(reply.contains(expected), "{reply}");
+        }
+        assert_eq!(replies[5], r#"{"status":"ok"}"#);
+    }
+
+    #[tokio::test]
+    async fn rejects_oversized_requests() {
+        let line = "x".repeat(MAX_REQUEST_SIZE as usize);
+        let replies = exchange(&[&line]).await;
+        assert_eq!(replies, [r#"{"status":"error","message":"request too large"}"#]);
+    }
+}
@@ -0,0 +1,74 @@
+use anyhow::{Context, Result, bail};
+use std::fs::{self, DirBuilder};
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::PathBuf;
+
+pub const DEFAULT_INSTANCE: &str = "default";
+
+/// Directory holding one socket per running instance
+///
+/// This is `$XDG_RUNTIME_DIR/barrs`, falling back to a per-user
+/// directory in `/tmp` when the runtime directory is not set.
+fn socket_dir() -> PathBuf {
+    match dirs::runtime_dir() {
+        Some(dir) => dir.join("barrs"),
+        None => std::env::temp_dir().join(format!("barrs-{}", current_uid())),
+    }
+}
+
+fn current_uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+/// Socket path of an instance, whose name has to be a plain file name
+pub fn socket_path(instance: &str) -> Result<PathBuf> {
+    if instance.is_empty() || instance.contains('/') || instance == "." || instance == ".." {
+        bail!("invalid instance name {:?}", instance);
+    }
+    Ok(socket_dir().join(format!("{instance}.sock")))
+}
+
+/// Create the socket directory, readable only by the current user
+///
+/// An existing directory is only used when it belongs to the current
+/// user and nobody else can access it, since another user could have
+/// created it first, e.g. in `/tmp`.
+pub fn prepare_socket_dir() -> Result<()> {
+    let dir = socket_dir();
+    DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)
+        .with_context(|| format!("failed to create socket directory {:?}", dir))?;
+
+    let metadata = fs::symlink_metadata(&dir)
+        .with_context(|| format!("failed to read socket directory {:?}", dir))?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() {
+        bail!("socket directory {:?} is not a directory owned by the current user", dir);
+    }
+    if metadata.mode() & 0o777 != 0o700 {
+        bail!("socket directory {:?} has mode {:o} instead of 700", dir, metadata.mode() & 0o777);
+    }
+    Ok(())
+}
+
+/// Names and socket paths of all instances that have a socket file
+pub fn instance_sockets() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(socket_dir()) else {
+        return Vec::new();
+    };
+
+    let mut sockets: Vec<(String, PathBuf)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| {
+            if path.extension()? != "sock" {
+                return None;
+            }
+            let instance = path.file_stem()?.to_string_lossy().into_owned();
+            Some((instance, path))
+        })
+        .collect();
+    sockets.sort();
+    sockets
+}
@@ -1,8 +1,13 @@
 mod config;
-pub use config::Config;
+pub use config::{Config, Diagnostic, OutputConfig, Severity};
 
 pub mod bar;
-pub use bar::Bar;
+pub use bar::{Bar, BarBuilder};
+
+pub mod ipc;
+
+mod module_error;
+pub use module_error::ModuleError;
 
 mod module_struct;
 pub use module_struct::ModuleOutput;
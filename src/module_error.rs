@@ -0,0 +1,49 @@
+use serde::Serialize;
+use std::fmt;
+
+/// Failure reported by a module alongside its output
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", content = "message", rename_all = "lowercase")]
+pub enum ModuleError {
+    /// An external command could not be run or exited unsuccessfully
+    Command(String),
+    /// A file, device or interface the module reads from is missing
+    Unavailable(String),
+    /// Data was read but could not be understood
+    Parse(String),
+    /// The X server could not be reached or queried
+    X11(String),
+    /// The module is not configured correctly
+    Config(String),
+}
+
+impl ModuleError {
+    /// Short name of the error kind, used by the `{kind}` placeholder
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ModuleError::Command(_) => "command",
+            ModuleError::Unavailable(_) => "unavailable",
+            ModuleError::Parse(_) => "parse",
+            ModuleError::X11(_) => "x11",
+            ModuleError::Config(_) => "config",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ModuleError::Command(m)
+            | ModuleError::Unavailable(m)
+            | ModuleError::Parse(m)
+            | ModuleError::X11(m)
+            | ModuleError::Config(m) => m,
+        }
+    }
+}
+
+impl fmt::Display for ModuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for ModuleError {}
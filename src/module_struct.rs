@@ -1,5 +1,41 @@
+use serde::Serialize;
+use super::ModuleError;
+
+#[derive(Default, Serialize)]
 pub struct ModuleOutput {
     pub icon: Option<String>,
     pub icon_color: Option<String>,
     pub value: String,
+    pub color: Option<String>,
+    pub error: Option<ModuleError>,
+    /// What the module is reporting on, e.g. `charging` for a battery,
+    /// which `hide_when.state` is compared with
+    pub state: Option<String>,
+    /// Ask the frontend to make the module stand out
+    pub urgent: bool,
+}
+
+impl ModuleOutput {
+    /// Build an output from the result of a module update, keeping
+    /// the error separate from the value so the bar can style it
+    pub fn from_result(
+        icon: Option<String>,
+        icon_color: Option<String>,
+        result: Result<String, ModuleError>,
+    ) -> Self {
+        match result {
+            Ok(value) => Self {
+                icon,
+                icon_color,
+                value,
+                ..Default::default()
+            },
+            Err(error) => Self {
+                icon,
+                icon_color,
+                error: Some(error),
+                ..Default::default()
+            },
+        }
+    }
 }
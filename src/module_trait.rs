@@ -1,12 +1,64 @@
 use async_trait::async_trait;
 use super::ModuleOutput;
+use super::bar::Monitor;
 
 #[async_trait]
 pub trait Module: Send + Sync {
+    /// Keep the module up to date, e.g. by calling `update` on an interval
     async fn run(&self);
+
+    /// Refresh the module once and notify the bar
+    async fn update(&self);
+
     async fn get_value(&self) -> ModuleOutput;
 
+    /// What the module shows on one monitor when the bar is drawn per
+    /// monitor, for modules whose state differs between monitors
+    async fn get_value_on(&self, _monitor: &Monitor) -> ModuleOutput {
+        self.get_value().await
+    }
+
     fn signal_id(&self) -> Option<u8> {
         None
     }
+
+    /// Seconds between updates, for modules that poll
+    fn interval(&self) -> Option<u64> {
+        None
+    }
+
+    /// Accept a value pushed with `barrs set`, returning whether the
+    /// module supports it. `expire` is in seconds.
+    fn set_value(&self, _text: String, _color: Option<String>, _expire: Option<u64>) -> bool {
+        false
+    }
+
+    /// Switch between the module's formats and its `format_alt*`
+    /// formats, returning whether it has any
+    fn toggle(&self) -> bool {
+        false
+    }
+
+    /// Whether the module applies its `format_alt*` and
+    /// `format_short*` formats itself, to what it last read, instead of
+    /// being built again with them
+    fn switches_formats(&self) -> bool {
+        false
+    }
+
+    /// What the module shows with its `format_short*` formats, for
+    /// modules that switch formats themselves
+    async fn get_short_value(&self) -> Option<ModuleOutput> {
+        None
+    }
+
+    /// Mouse buttons the module reacts to in `click`, so frontends only
+    /// add click areas for buttons that do something
+    fn buttons(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    /// Handle a mouse button pressed on the module, where buttons 4
+    /// and 5 are scrolling up and down
+    async fn click(&self, _button: u8) {}
 }